@@ -116,6 +116,8 @@ pub enum Kind {
     IoError(std::io::ErrorKind),
     ImageError,
     InvalidFont,
+    InvalidChunk,
+    TextOverflow,
     BigImgBuilderError,
     TryFromIntError,
     Other,
@@ -1,3 +1,4 @@
+use super::super::rect::Rect;
 use image::{GenericImage, GenericImageView, Pixel};
 
 /// A surface for drawing on - many drawing functions in this
@@ -115,3 +116,30 @@ impl<I: GenericImage> Canvas for Blend<I> {
         self.0.put_pixel(x, y, pix);
     }
 }
+
+/// A canvas that only draws pixels inside `rect`.
+pub(super) struct Clipped<'a, C: ?Sized> {
+    pub canvas: &'a mut C,
+    pub rect: Rect,
+}
+
+impl<C: Canvas + ?Sized> Canvas for Clipped<'_, C> {
+    type Pixel = C::Pixel;
+
+    fn dimensions(&self) -> (u32, u32) {
+        self.canvas.dimensions()
+    }
+
+    fn get_pixel(&self, x: u32, y: u32) -> Self::Pixel {
+        self.canvas.get_pixel(x, y)
+    }
+
+    fn draw_pixel(&mut self, x: u32, y: u32, color: Self::Pixel) {
+        let (x_, y_) = (x as i32, y as i32);
+        if (self.rect.left()..=self.rect.right()).contains(&x_)
+            && (self.rect.top()..=self.rect.bottom()).contains(&y_)
+        {
+            self.canvas.draw_pixel(x, y, color);
+        }
+    }
+}
@@ -489,6 +489,8 @@ where
 
     fn draw_filled_rounded_rect_mut(&mut self, rect: Rect, radius: i32, color: Self::Pixel) {
         let (left, right, top, bottom) = (rect.left(), rect.right(), rect.top(), rect.bottom());
+        // 圆角不超过短边的一半，去掉圆角后的矩形不为空，窄矩形也能绘制
+        let radius = radius.clamp(0, ((rect.width().min(rect.height()) - 1) / 2) as i32);
         // 绘制四个圆角
         self.draw_filled_circle_mut((left + radius, top + radius), radius, color);
         self.draw_filled_circle_mut((left + radius, bottom - radius), radius, color);
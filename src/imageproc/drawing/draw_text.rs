@@ -1,44 +1,158 @@
 use super::{
     super::{definitions::Clamp, pixelops::weighted_sum, rect},
+    effect::{draw_effect, TextEffect},
+    fonts::{line_box, Fonts},
+    glyph_cache::{Coverage, GlyphCache},
+    layout::{fit_layout, LineBox, ShapedLine, TextLayout},
+    rich_text::{draw_rich_text, plain_runs, Run},
+    shaping::{shape_text, PositionedGlyph},
+    text_style::{HAlign, TextOverflow, TextStyle, VAlign},
+    vertical::draw_vertical_center,
+    wrap::{fit_with, wrap},
     Canvas,
 };
-use ab_glyph::{point, Font, GlyphId, OutlinedGlyph, PxScale, ScaleFont};
+use ab_glyph::{Font as _, PxScale};
 use image::Pixel;
 
-fn layout_glyphs(
+/// Call `f` with the cached coverage of every glyph in `glyphs` and the pixel its
+/// coverage starts at, relative to the text origin. Returns the tallest coverage.
+pub(super) fn draw_glyphs<T: Fonts>(
+    font: &T,
+    glyphs: &[PositionedGlyph<T::Font>],
+    mut f: impl FnMut(&Coverage, i32, i32),
+) -> u32 {
+    let cache = GlyphCache::global();
+    let mut h = 0u32;
+    for g in glyphs {
+        if let Some((coverage, left, top)) =
+            cache.get(g.font, font.font_id(g.font), g.glyph.clone())
+        {
+            h = h.max(coverage.size().1);
+            f(&coverage, left, top);
+        }
+    }
+    h
+}
+
+/// Lay out `text` and call `f` like [`draw_glyphs`].
+pub(super) fn layout_glyphs(
     scale: impl Into<PxScale> + Copy,
-    font: &impl Font,
+    font: &impl Fonts,
     text: &str,
-    mut f: impl FnMut(OutlinedGlyph, ab_glyph::Rect),
+    f: impl FnMut(&Coverage, i32, i32),
 ) -> (u32, u32) {
-    let (mut w, mut h) = (0f32, 0f32);
-
-    let font = font.as_scaled(scale);
-    let mut last: Option<GlyphId> = None;
-
-    for c in text.chars() {
-        let glyph_id = font.glyph_id(c);
-        let glyph = glyph_id.with_scale_and_position(scale, point(w, font.ascent()));
-        w += font.h_advance(glyph_id);
-        if let Some(g) = font.outline_glyph(glyph) {
-            if let Some(last) = last {
-                w += font.kern(glyph_id, last);
-            }
-            last = Some(glyph_id);
-            let bb = g.px_bounds();
-            h = h.max(bb.height());
-            f(g, bb);
-        }
-    }
+    let run = shape_text(scale, font, text);
+    let h = draw_glyphs(font, &run.glyphs, f);
+    (run.width as u32, h)
+}
+
+/// Blend `glyphs` in `color` into `canvas`, the text origin at (`x`, `y`).
+fn blend_glyphs<C, T>(
+    canvas: &mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    font: &T,
+    glyphs: &[PositionedGlyph<T::Font>],
+) where
+    C: Canvas + ?Sized,
+    <C::Pixel as Pixel>::Subpixel: Into<f32> + Clamp<f32>,
+    T: Fonts,
+{
+    let image_width = canvas.width() as i32;
+    let image_height = canvas.height() as i32;
+
+    draw_glyphs(font, glyphs, |g, left, top| {
+        g.draw(|gx, gy, gv| {
+            let image_x = gx as i32 + x + left;
+            let image_y = gy as i32 + y + top;
 
-    (w as u32, h as u32)
+            if (0..image_width).contains(&image_x) && (0..image_height).contains(&image_y) {
+                let image_x = image_x as u32;
+                let image_y = image_y as u32;
+                let pixel = canvas.get_pixel(image_x, image_y);
+                let weighted_color = weighted_sum(pixel, color, 1.0 - gv, gv);
+                canvas.draw_pixel(image_x, image_y, weighted_color);
+            }
+        });
+    });
 }
 
 /// Get the width and height of the given text, rendered with the given font and scale.
 ///
-/// Note that this function *does not* support newlines, you must do this manually.
-pub fn text_size(scale: impl Into<PxScale> + Copy, font: &impl Font, text: &str) -> (u32, u32) {
-    layout_glyphs(scale, font, text, |_, _| {})
+/// Note that this function *does not* support newlines and its height is the height
+/// of the glyphs, not the line height. Use [`layout_text`](super::layout_text) to measure lines.
+pub fn text_size(scale: impl Into<PxScale> + Copy, font: &impl Fonts, text: &str) -> (u32, u32) {
+    // 只测量轮廓边界，不光栅化
+    let run = shape_text(scale, font, text);
+    let h = run
+        .glyphs
+        .into_iter()
+        .filter_map(|PositionedGlyph { font, glyph }| font.outline_glyph(glyph))
+        .map(|g| g.px_bounds().height())
+        .fold(0f32, f32::max);
+    (run.width as u32, h as u32)
+}
+
+/// Wrap the given text into lines no wider than `width`.
+///
+/// Explicit newlines are kept. Latin text wraps between words, CJK text wraps
+/// between characters without starting a line with closing punctuation.
+/// Every line is trimmed.
+pub fn wrap_text<'a>(
+    scale: impl Into<PxScale> + Copy,
+    font: &impl Fonts,
+    text: &'a str,
+    width: u32,
+) -> Vec<&'a str> {
+    text.lines()
+        .flat_map(|line| {
+            wrap(line, width as f32, |r| {
+                text_size(scale, font, &line[r]).0 as f32
+            })
+            .into_iter()
+            .map(move |r| &line[r])
+        })
+        .collect()
+}
+
+/// Get the scale and wrapped lines with which the given text fits into a `width` x `height` box.
+///
+/// The text is first wrapped to `width`. Only if the wrapped lines still do not fit,
+/// the scale is shrunk and the text wrapped again.
+pub fn fit_text<'a>(
+    scale: impl Into<PxScale> + Copy,
+    font: &impl Fonts,
+    text: &'a str,
+    width: u32,
+    height: u32,
+) -> (PxScale, Vec<&'a str>) {
+    fit_with(scale.into(), 0.0, width, height, |scale| {
+        let lines = wrap_text(scale, font, text, width);
+
+        // 计算换行后的文本尺寸
+        let row = u32::try_from(lines.len()).unwrap();
+        let text_height = row * line_box(font, scale).1 as u32;
+        let text_width = lines
+            .iter()
+            .map(|line| text_size(scale, font, line).0)
+            .max()
+            .unwrap_or(0);
+        (lines, (text_width, text_height))
+    })
+}
+
+/// Get the scale at which the given text fits into a `width` x `height` box.
+///
+/// See [`fit_text`], if the text fits at `scale` after wrapping, `scale` is returned unchanged.
+pub fn fit_scale(
+    scale: impl Into<PxScale> + Copy,
+    font: &impl Fonts,
+    text: &str,
+    width: u32,
+    height: u32,
+) -> PxScale {
+    fit_text(scale, font, text, width, height).0
 }
 
 pub trait DrawText: Canvas
@@ -53,82 +167,163 @@ where
         x: i32,
         y: i32,
         scale: impl Into<PxScale> + Copy,
-        font: &impl Font,
+        font: &impl Fonts,
         text: &str,
     ) {
-        let image_width = self.width() as i32;
-        let image_height = self.height() as i32;
-
-        layout_glyphs(scale, font, text, |g, bb| {
-            g.draw(|gx, gy, gv| {
-                let image_x = gx as i32 + x + bb.min.x.round() as i32;
-                let image_y = gy as i32 + y + bb.min.y.round() as i32;
-                let gv = gv.clamp(0.0, 1.0);
-
-                if (0..image_width).contains(&image_x) && (0..image_height).contains(&image_y) {
-                    let image_x = image_x as u32;
-                    let image_y = image_y as u32;
-                    let pixel = self.get_pixel(image_x, image_y);
-                    let weighted_color = weighted_sum(pixel, color, 1.0 - gv, gv);
-                    self.draw_pixel(image_x, image_y, weighted_color);
-                }
-            })
-        });
+        let run = shape_text(scale, font, text);
+        blend_glyphs(self, color, x, y, font, &run.glyphs);
+    }
+
+    /// Draw text with a shadow and an outline behind it, see [`TextEffect`].
+    #[allow(clippy::too_many_arguments)]
+    fn draw_text_with_effect_mut(
+        &mut self,
+        color: Self::Pixel,
+        x: i32,
+        y: i32,
+        scale: impl Into<PxScale> + Copy,
+        font: &impl Fonts,
+        text: &str,
+        effect: &TextEffect<Self::Pixel>,
+    ) {
+        let run = shape_text(scale, font, text);
+        draw_effect(self, x, y, font, &run.glyphs, effect);
+        blend_glyphs(self, color, x, y, font, &run.glyphs);
     }
 
+    /// Draw text wrapped and centered in `rect`, shrinking it as far as needed to fit.
+    ///
+    /// Use [`Self::draw_text_styled_mut`] for a minimum scale and an overflow policy.
     fn draw_text_center_mut(
         &mut self,
         color: Self::Pixel,
         rect: rect::Rect,
         scale: impl Into<PxScale> + Copy,
-        font: &impl Font,
+        font: &impl Fonts,
         text: &str,
     ) {
-        // 将文本按行分割并去除每行的前后空格
-        let lines: Vec<&str> = text.lines().map(str::trim).collect();
-
-        // 计算文本原始高度
-        let row = u32::try_from(lines.len()).unwrap();
-        let text_raw_height = row * font.as_scaled(scale).height() as u32;
+        self.draw_text_center_with_effect_mut(
+            color,
+            rect,
+            scale,
+            font,
+            text,
+            &TextEffect::default(),
+        );
+    }
 
-        // 计算文本原始宽度
-        let text_raw_width = lines
-            .iter()
-            .map(|line| text_size(scale, &font, line).0)
-            .max()
-            .unwrap_or(0);
+    /// Draw text centered in `rect` like [`Self::draw_text_center_mut`], with effects behind it.
+    fn draw_text_center_with_effect_mut(
+        &mut self,
+        color: Self::Pixel,
+        rect: rect::Rect,
+        scale: impl Into<PxScale> + Copy,
+        font: &impl Fonts,
+        text: &str,
+        effect: &TextEffect<Self::Pixel>,
+    ) {
+        // 先按矩形宽度自动换行，仍放不下时再缩小字体
+        let mut layout = fit_layout(scale, font, text, rect.width(), rect.height());
+        layout.align(rect, HAlign::Center, VAlign::Middle);
+        self.draw_layout_mut(color, font, &layout, effect);
+    }
 
-        // 解构矩形区域
-        let (rect_left, rect_top, rect_width, rect_height) =
-            (rect.left(), rect.top(), rect.width(), rect.height());
-
-        // 根据矩形区域和文本原始尺寸计算最终字体大小
-        let scale = if text_raw_width > rect_width || text_raw_height > rect_height {
-            let x_radio = rect_width as f32 / text_raw_width as f32;
-            let y_radio = rect_height as f32 / text_raw_height as f32;
-            PxScale::from(scale.into().x * (x_radio.min(y_radio)))
-        } else {
-            scale.into()
+    /// Draw every line of `layout` at its position, with the effects of all lines behind the text.
+    ///
+    /// The glyphs shaped by the layout are drawn as they are, without shaping the text again.
+    fn draw_layout_mut<T: Fonts>(
+        &mut self,
+        color: Self::Pixel,
+        font: &T,
+        layout: &TextLayout<ShapedLine<T::Font>>,
+        effect: &TextEffect<Self::Pixel>,
+    ) {
+        // 文字的原点在主字体的上沿
+        let origin = |line: &LineBox<ShapedLine<T::Font>>| {
+            let (x, baseline) = layout.origin(line);
+            (x.round() as i32, (baseline - line.ascent).round() as i32)
         };
-
-        // 重新计算文本高度
-        let h = font.as_scaled(scale).height() as u32;
-
-        // 计算文本顶部位置
-        let top_ = rect_top + i32::try_from(rect_height - h * row).unwrap() / 2;
-
-        // 遍历每行文本并绘制
-        for (row, line) in lines.iter().enumerate() {
-            self.draw_text_mut(
-                color,
-                rect_left
-                    + i32::try_from((rect_width - text_size(scale, font, line).0) / 2).unwrap(),
-                top_ + i32::try_from(h).unwrap() * i32::try_from(row).unwrap(),
-                scale,
-                font,
-                line,
-            );
+        // 先画出所有行的效果，避免后面行的描边和阴影盖住前面行的文字
+        for line in &layout.lines {
+            let (x, y) = origin(line);
+            draw_effect(self, x, y, font, &line.content.glyphs, effect);
         }
+        for line in &layout.lines {
+            let (x, y) = origin(line);
+            blend_glyphs(self, color, x, y, font, &line.content.glyphs);
+        }
+    }
+
+    /// Draw text vertically, top to bottom in columns from right to left, centered in `rect`.
+    ///
+    /// CJK characters stand upright, brackets and Latin text are rotated and commas and
+    /// full stops move to the top-right of their cell. Columns wrap and shrink to fit
+    /// like [`Self::draw_text_center_mut`].
+    fn draw_text_vertical_center_mut(
+        &mut self,
+        color: Self::Pixel,
+        rect: rect::Rect,
+        scale: impl Into<PxScale> + Copy,
+        font: &impl Fonts,
+        text: &str,
+    ) {
+        draw_vertical_center(self, color, rect, scale, font, text);
+    }
+
+    /// Draw lines of styled runs centered in `rect`, see [`parse_markup`](super::parse_markup).
+    ///
+    /// Runs without a color use `color`. Plain text is laid out like [`Self::draw_text_center_mut`],
+    /// `effect` is drawn behind the whole text.
+    fn draw_rich_text_center_mut(
+        &mut self,
+        color: Self::Pixel,
+        rect: rect::Rect,
+        scale: impl Into<PxScale> + Copy,
+        font: &impl Fonts,
+        lines: &[Vec<Run<Self::Pixel>>],
+        effect: &TextEffect<Self::Pixel>,
+    ) where
+        Self: Sized,
+    {
+        let style = TextStyle::new(scale);
+        draw_rich_text(self, color, rect, font, lines, &style, effect);
+    }
+
+    /// Draw lines of styled runs in `rect`, aligned and padded by `style`.
+    ///
+    /// Lines wrap to the width of the box, then shrink within the scale range of `style`.
+    /// Returns the overflow if the text does not fit at the minimum scale, see [`Overflow`](super::Overflow).
+    fn draw_rich_text_mut(
+        &mut self,
+        color: Self::Pixel,
+        rect: rect::Rect,
+        font: &impl Fonts,
+        lines: &[Vec<Run<Self::Pixel>>],
+        style: &TextStyle,
+        effect: &TextEffect<Self::Pixel>,
+    ) -> Option<TextOverflow>
+    where
+        Self: Sized,
+    {
+        draw_rich_text(self, color, rect, font, lines, style, effect)
+    }
+
+    /// Draw plain text in `rect`, aligned and padded by `style`.
+    ///
+    /// Returns the overflow like [`Self::draw_rich_text_mut`].
+    fn draw_text_styled_mut(
+        &mut self,
+        color: Self::Pixel,
+        rect: rect::Rect,
+        font: &impl Fonts,
+        text: &str,
+        style: &TextStyle,
+        effect: &TextEffect<Self::Pixel>,
+    ) -> Option<TextOverflow>
+    where
+        Self: Sized,
+    {
+        draw_rich_text(self, color, rect, font, &plain_runs(text), style, effect)
     }
 }
 
@@ -0,0 +1,275 @@
+use super::{
+    super::{definitions::Clamp, pixelops::weighted_sum},
+    draw_text::draw_glyphs,
+    fonts::Fonts,
+    shaping::PositionedGlyph,
+    Canvas,
+};
+use image::Pixel;
+
+/// An outline drawn around every glyph.
+///
+/// `width` is the stroke width in pixels outside the glyph edge.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Outline<P> {
+    pub width: f32,
+    pub color: P,
+}
+
+/// A soft shadow drawn below the text.
+///
+/// `offset` moves the shadow relative to the text, `blur` is the blur radius
+/// in pixels and `opacity` scales the shadow coverage, in range `0.0..=1.0`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shadow<P> {
+    pub offset: (i32, i32),
+    pub blur: f32,
+    pub opacity: f32,
+    pub color: P,
+}
+
+/// Effects drawn behind text: a drop shadow, then an outline, then the text itself.
+///
+/// If there is an outline, the shadow is cast by the outlined text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextEffect<P> {
+    pub outline: Option<Outline<P>>,
+    pub shadow: Option<Shadow<P>>,
+}
+
+impl<P> Default for TextEffect<P> {
+    fn default() -> Self {
+        Self {
+            outline: None,
+            shadow: None,
+        }
+    }
+}
+
+impl<P> TextEffect<P> {
+    /// Whether no effect is drawn.
+    pub fn is_none(&self) -> bool {
+        self.outline.is_none() && self.shadow.is_none()
+    }
+
+    /// How far the effects reach outside the glyph bounds, in pixels.
+    pub fn margin(&self) -> u32 {
+        let outline = self.outline.as_ref().map_or(0.0, |o| o.width.max(0.0));
+        let shadow = self.shadow.as_ref().map_or(0.0, |s| {
+            s.blur.max(0.0) * 2.0 + s.offset.0.abs().max(s.offset.1.abs()) as f32
+        });
+        (outline + shadow).ceil() as u32
+    }
+
+    /// Scale outline width, shadow offset and blur radius by `factor`.
+    #[must_use]
+    pub fn scaled(mut self, factor: f32) -> Self {
+        if let Some(ref mut outline) = self.outline {
+            outline.width *= factor;
+        }
+        if let Some(ref mut shadow) = self.shadow {
+            shadow.offset = (
+                (shadow.offset.0 as f32 * factor).round() as i32,
+                (shadow.offset.1 as f32 * factor).round() as i32,
+            );
+            shadow.blur *= factor;
+        }
+        self
+    }
+}
+
+/// Text coverage in a rectangle, relative to the text origin.
+#[derive(Clone)]
+struct Mask {
+    left: i32,
+    top: i32,
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+}
+
+impl Mask {
+    fn get(&self, x: isize, y: isize) -> f32 {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            0.0
+        } else {
+            self.data[y as usize * self.width + x as usize]
+        }
+    }
+
+    /// Grow the coverage by `radius`, anti-aliased at the edge.
+    fn dilate(&self, radius: f32) -> Self {
+        let r = radius.ceil() as isize;
+        let mut out = self.clone();
+        for y in 0..self.height as isize {
+            for x in 0..self.width as isize {
+                let mut v = 0f32;
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        let dist = ((dx * dx + dy * dy) as f32).sqrt();
+                        let weight = (radius + 0.5 - dist).clamp(0.0, 1.0);
+                        if weight > 0.0 {
+                            v = v.max(self.get(x + dx, y + dy) * weight);
+                        }
+                    }
+                }
+                out.data[y as usize * self.width + x as usize] = v;
+            }
+        }
+        out
+    }
+
+    /// Approximate a gaussian blur of `radius` with repeated box blurs.
+    fn blur(&self, radius: f32) -> Self {
+        let r = (radius / 2.0).round() as usize;
+        let mut out = self.clone();
+        if r == 0 {
+            return out;
+        }
+        for _ in 0..2 {
+            out.box_blur(r, 1, self.width, self.height, self.width);
+            out.box_blur(r, self.width, self.height, self.width, 1);
+        }
+        out
+    }
+
+    /// Box blur `lines` lines of `len` values, `step` apart inside a line and `stride` apart between lines.
+    fn box_blur(&mut self, r: usize, step: usize, len: usize, lines: usize, stride: usize) {
+        let mut line = vec![0f32; len];
+        let n = (2 * r + 1) as f32;
+        for l in 0..lines {
+            let at = |i: usize| l * stride + i * step;
+            for (i, v) in line.iter_mut().enumerate() {
+                *v = self.data[at(i)];
+            }
+            let mut sum: f32 = line.iter().take(r + 1).sum();
+            for i in 0..len {
+                self.data[at(i)] = sum / n;
+                if i + r + 1 < len {
+                    sum += line[i + r + 1];
+                }
+                if i >= r {
+                    sum -= line[i - r];
+                }
+            }
+        }
+    }
+
+    /// Blend `color` into `canvas` with the mask as coverage, the text origin at (`x`, `y`).
+    fn blend<C>(&self, canvas: &mut C, x: i32, y: i32, color: C::Pixel, opacity: f32)
+    where
+        C: Canvas + ?Sized,
+        <C::Pixel as Pixel>::Subpixel: Into<f32> + Clamp<f32>,
+    {
+        let (image_width, image_height) = (canvas.width() as i32, canvas.height() as i32);
+        for (i, v) in self.data.iter().enumerate() {
+            let gv = (v * opacity).clamp(0.0, 1.0);
+            if gv <= 0.0 {
+                continue;
+            }
+            let image_x = x + self.left + (i % self.width) as i32;
+            let image_y = y + self.top + (i / self.width) as i32;
+            if (0..image_width).contains(&image_x) && (0..image_height).contains(&image_y) {
+                let (image_x, image_y) = (image_x as u32, image_y as u32);
+                let pixel = canvas.get_pixel(image_x, image_y);
+                let weighted_color = weighted_sum(pixel, color, 1.0 - gv, gv);
+                canvas.draw_pixel(image_x, image_y, weighted_color);
+            }
+        }
+    }
+}
+
+/// Draw the shadow and outline of shaped `glyphs` at (`x`, `y`), without the text itself.
+pub(super) fn draw_effect<C, T>(
+    canvas: &mut C,
+    x: i32,
+    y: i32,
+    font: &T,
+    glyphs: &[PositionedGlyph<T::Font>],
+    effect: &TextEffect<C::Pixel>,
+) where
+    C: Canvas + ?Sized,
+    <C::Pixel as Pixel>::Subpixel: Into<f32> + Clamp<f32>,
+    T: Fonts,
+{
+    if effect.is_none() {
+        return;
+    }
+
+    // 收集文字的覆盖率
+    let mut points = Vec::new();
+    draw_glyphs(font, glyphs, |g, left, top| {
+        g.draw(|gx, gy, gv| {
+            points.push((gx as i32 + left, gy as i32 + top, gv));
+        });
+    });
+    let Some(bounds) = points
+        .iter()
+        .map(|&(px, py, _)| (px, py, px, py))
+        .reduce(|(x0, y0, x1, y1), (px, py, ..)| (x0.min(px), y0.min(py), x1.max(px), y1.max(py)))
+    else {
+        return;
+    };
+
+    let pad = effect.margin() as i32 + 1;
+    let (left, top) = (bounds.0 - pad, bounds.1 - pad);
+    let width = (bounds.2 - left + pad + 1) as usize;
+    let height = (bounds.3 - top + pad + 1) as usize;
+    let mut mask = Mask {
+        left,
+        top,
+        width,
+        height,
+        data: vec![0.0; width * height],
+    };
+    for (px, py, gv) in points {
+        let i = (py - top) as usize * width + (px - left) as usize;
+        mask.data[i] = mask.data[i].max(gv);
+    }
+
+    let stroked = match effect.outline {
+        Some(ref outline) => mask.dilate(outline.width),
+        None => mask,
+    };
+    if let Some(ref shadow) = effect.shadow {
+        stroked.blur(shadow.blur).blend(
+            canvas,
+            x + shadow.offset.0,
+            y + shadow.offset.1,
+            shadow.color,
+            shadow.opacity,
+        );
+    }
+    if let Some(ref outline) = effect.outline {
+        stroked.blend(canvas, x, y, outline.color, 1.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dilate_blur() {
+        let mut mask = Mask {
+            left: 0,
+            top: 0,
+            width: 9,
+            height: 9,
+            data: vec![0.0; 81],
+        };
+        mask.data[4 * 9 + 4] = 1.0;
+
+        let stroked = mask.dilate(2.0);
+        // 描边边缘半覆盖
+        assert_eq!(stroked.get(5, 4), 1.0);
+        assert_eq!(stroked.get(6, 4), 0.5);
+        assert_eq!(stroked.get(7, 4), 0.0);
+
+        // 模糊保持总覆盖率不变
+        let blurred = stroked.blur(2.0);
+        let total = |m: &Mask| m.data.iter().sum::<f32>();
+        assert!((total(&blurred) - total(&stroked)).abs() < 1e-3);
+        assert!(blurred.get(4, 4) < 1.0);
+    }
+}
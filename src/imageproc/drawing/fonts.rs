@@ -0,0 +1,320 @@
+use super::glyph_cache::FontId;
+use ab_glyph::{Font, FontArc, FontVec, InvalidFont, PxScale, ScaleFont};
+use rustybuzz::Face;
+
+/// Fonts used to draw text, picked per character.
+///
+/// Every [`Font`] is a set of one font. Line metrics always come from
+/// the primary font, so glyphs from different fonts share a baseline.
+pub trait Fonts {
+    type Font: Font;
+
+    /// The font providing line metrics.
+    fn primary(&self) -> &Self::Font;
+
+    /// The font drawing `c`, the primary font if no font has it.
+    fn font_for(&self, c: char) -> &Self::Font;
+
+    /// Line height as a multiple of the height of the primary font, set by [`TextStyle`](super::TextStyle).
+    fn line_height(&self) -> f32 {
+        1.0
+    }
+
+    /// Extra space between characters in ems, set by [`TextStyle`](super::TextStyle).
+    fn letter_spacing(&self) -> f32 {
+        0.0
+    }
+
+    /// The id of `font` in the glyph cache, `None` to rasterize its glyphs every time.
+    fn font_id(&self, _font: &Self::Font) -> Option<FontId> {
+        None
+    }
+
+    /// The font file of `font` for text shaping, `None` to lay out characters one by one.
+    fn shaping_data(&self, _font: &Self::Font) -> Option<&[u8]> {
+        None
+    }
+}
+
+/// The ascent and height of a line box at `scale`.
+///
+/// Extra line height is split evenly above and below the glyphs.
+pub(super) fn line_box(font: &impl Fonts, scale: PxScale) -> (f32, f32) {
+    let metrics = font.primary().as_scaled(scale);
+    let height = metrics.height() * font.line_height();
+    (metrics.ascent() + (height - metrics.height()) / 2.0, height)
+}
+
+impl<F: Font> Fonts for F {
+    type Font = F;
+
+    fn primary(&self) -> &F {
+        self
+    }
+
+    fn font_for(&self, _: char) -> &F {
+        self
+    }
+}
+
+/// An ordered list of fonts, each character is drawn with the first font that has it.
+///
+/// Fonts created from their font file are shaped, see [`shape_text`](super::shape_text).
+#[derive(Clone, Debug)]
+pub struct FontChain {
+    fonts: Vec<FontArc>,
+    ids: Vec<FontId>,
+    shaped: Vec<bool>,
+}
+
+impl FontChain {
+    /// Create a chain with a single primary font.
+    #[must_use]
+    pub fn new(primary: FontArc) -> Self {
+        Self {
+            fonts: vec![primary],
+            ids: vec![FontId::new()],
+            shaped: vec![false],
+        }
+    }
+
+    /// Create a chain with a single primary font from its font file, enabling text shaping.
+    ///
+    /// # Errors
+    /// If `data` is not a valid font.
+    pub fn from_data(data: impl Into<Vec<u8>>) -> Result<Self, InvalidFont> {
+        let (font, shaped) = parse(data)?;
+        let mut chain = Self::new(font);
+        chain.shaped[0] = shaped;
+        Ok(chain)
+    }
+
+    /// Append a fallback font to the end of the chain.
+    #[must_use]
+    pub fn with_fallback(mut self, font: FontArc) -> Self {
+        self.fonts.push(font);
+        self.ids.push(FontId::new());
+        self.shaped.push(false);
+        self
+    }
+
+    /// Append a fallback font from its font file, enabling text shaping.
+    ///
+    /// # Errors
+    /// If `data` is not a valid font.
+    pub fn with_fallback_data(mut self, data: impl Into<Vec<u8>>) -> Result<Self, InvalidFont> {
+        let (font, shaped) = parse(data)?;
+        self.fonts.push(font);
+        self.ids.push(FontId::new());
+        self.shaped.push(shaped);
+        Ok(self)
+    }
+
+    /// Replace the primary font, keeping the fallback fonts.
+    #[must_use]
+    pub fn with_primary(mut self, font: FontArc) -> Self {
+        self.fonts[0] = font;
+        self.ids[0] = FontId::new();
+        self.shaped[0] = false;
+        self
+    }
+
+    /// Replace the primary font with a font file, keeping the fallback fonts.
+    ///
+    /// # Errors
+    /// If `data` is not a valid font.
+    pub fn with_primary_data(mut self, data: impl Into<Vec<u8>>) -> Result<Self, InvalidFont> {
+        let (font, shaped) = parse(data)?;
+        self.fonts[0] = font;
+        self.ids[0] = FontId::new();
+        self.shaped[0] = shaped;
+        Ok(self)
+    }
+
+    /// Replace the primary font with the primary font of `other`, keeping the fallback fonts.
+    ///
+    /// Unlike [`with_primary_data`](Self::with_primary_data) the font is not parsed again.
+    #[must_use]
+    pub fn with_primary_of(mut self, other: &Self) -> Self {
+        self.fonts[0] = other.fonts[0].clone();
+        self.ids[0] = other.ids[0];
+        self.shaped[0] = other.shaped[0];
+        self
+    }
+
+    /// All fonts in order, the primary font first.
+    #[must_use]
+    pub fn fonts(&self) -> &[FontArc] {
+        &self.fonts
+    }
+}
+
+/// Parse a font file into a font, taking ownership of the data without copying it,
+/// and check whether `rustybuzz` can shape it.
+fn parse(data: impl Into<Vec<u8>>) -> Result<(FontArc, bool), InvalidFont> {
+    let font = FontArc::new(FontVec::try_from_vec(data.into())?);
+    let shaped = Face::from_slice(font.font_data(), 0).is_some();
+    Ok((font, shaped))
+}
+
+impl From<FontArc> for FontChain {
+    fn from(font: FontArc) -> Self {
+        Self::new(font)
+    }
+}
+
+impl Fonts for FontChain {
+    type Font = FontArc;
+
+    fn primary(&self) -> &FontArc {
+        &self.fonts[0]
+    }
+
+    fn font_for(&self, c: char) -> &FontArc {
+        // 字形 0 是 `.notdef`，即字体中没有该字符
+        self.fonts
+            .iter()
+            .find(|font| font.glyph_id(c).0 != 0)
+            .unwrap_or(&self.fonts[0])
+    }
+
+    fn font_id(&self, font: &FontArc) -> Option<FontId> {
+        let i = self.fonts.iter().position(|f| std::ptr::eq(f, font))?;
+        Some(self.ids[i])
+    }
+
+    fn shaping_data(&self, font: &FontArc) -> Option<&[u8]> {
+        let i = self.fonts.iter().position(|f| std::ptr::eq(f, font))?;
+        self.shaped[i].then(|| self.fonts[i].font_data())
+    }
+}
+
+/// Fonts drawing text with the line height and letter spacing of a [`TextStyle`](super::TextStyle).
+pub(super) struct Spaced<'a, F> {
+    pub(super) fonts: &'a F,
+    pub(super) line_height: f32,
+    pub(super) letter_spacing: f32,
+}
+
+impl<F: Fonts> Fonts for Spaced<'_, F> {
+    type Font = F::Font;
+
+    fn primary(&self) -> &F::Font {
+        self.fonts.primary()
+    }
+
+    fn font_for(&self, c: char) -> &F::Font {
+        self.fonts.font_for(c)
+    }
+
+    fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    fn letter_spacing(&self) -> f32 {
+        self.letter_spacing
+    }
+
+    fn font_id(&self, font: &F::Font) -> Option<FontId> {
+        self.fonts.font_id(font)
+    }
+
+    fn shaping_data(&self, font: &F::Font) -> Option<&[u8]> {
+        self.fonts.shaping_data(font)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imageproc::drawing::test_font;
+    use ab_glyph::{v2::GlyphImage, CodepointIdIter, GlyphId, Outline};
+
+    /// A font without the glyph of one character, as if the font file lacked it.
+    struct Without(FontArc, char);
+
+    impl Font for Without {
+        fn units_per_em(&self) -> Option<f32> {
+            self.0.units_per_em()
+        }
+        fn ascent_unscaled(&self) -> f32 {
+            self.0.ascent_unscaled()
+        }
+        fn descent_unscaled(&self) -> f32 {
+            self.0.descent_unscaled()
+        }
+        fn line_gap_unscaled(&self) -> f32 {
+            self.0.line_gap_unscaled()
+        }
+        fn glyph_id(&self, c: char) -> GlyphId {
+            if c == self.1 {
+                GlyphId(0)
+            } else {
+                self.0.glyph_id(c)
+            }
+        }
+        fn h_advance_unscaled(&self, id: GlyphId) -> f32 {
+            self.0.h_advance_unscaled(id)
+        }
+        fn h_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+            self.0.h_side_bearing_unscaled(id)
+        }
+        fn v_advance_unscaled(&self, id: GlyphId) -> f32 {
+            self.0.v_advance_unscaled(id)
+        }
+        fn v_side_bearing_unscaled(&self, id: GlyphId) -> f32 {
+            self.0.v_side_bearing_unscaled(id)
+        }
+        fn kern_unscaled(&self, first: GlyphId, second: GlyphId) -> f32 {
+            self.0.kern_unscaled(first, second)
+        }
+        fn outline(&self, id: GlyphId) -> Option<Outline> {
+            self.0.outline(id)
+        }
+        fn glyph_count(&self) -> usize {
+            self.0.glyph_count()
+        }
+        fn codepoint_ids(&self) -> CodepointIdIter<'_> {
+            self.0.codepoint_ids()
+        }
+        fn glyph_raster_image2(&self, id: GlyphId, size: u16) -> Option<GlyphImage<'_>> {
+            self.0.glyph_raster_image2(id, size)
+        }
+    }
+
+    #[test]
+    fn test_font_for() {
+        let font = test_font();
+        let chain = FontChain::new(FontArc::new(Without(font.clone(), 'a'))).with_fallback(font);
+        // 主字体有的字符使用主字体，主字体缺少的字符由回退字体绘制
+        assert!(std::ptr::eq(chain.font_for('b'), chain.primary()));
+        assert!(std::ptr::eq(chain.font_for('a'), &chain.fonts()[1]));
+        // 所有字体都没有的字符回退到主字体
+        assert!(std::ptr::eq(chain.font_for('\u{E000}'), chain.primary()));
+
+        // 回退字体的字形与主字体共用基线，宽度与只用回退字体时相同
+        let scale = PxScale::from(40.0);
+        let (mixed, _) = super::super::text_size(scale, &chain, "ab");
+        let (single, _) = super::super::text_size(scale, &chain.fonts()[1], "ab");
+        assert_eq!(mixed, single);
+    }
+
+    #[test]
+    fn test_spacing() {
+        let font = test_font();
+        let scale = PxScale::from(40.0);
+        let spaced = super::super::TextStyle::new(scale)
+            .line_height(1.5)
+            .letter_spacing(0.25)
+            .fonts(&font);
+        // 四个字符之间有三处字间距
+        let (plain, _) = super::super::text_size(scale, &font, "abcd");
+        let (wide, _) = super::super::text_size(scale, &spaced, "abcd");
+        assert!((wide as f32 - plain as f32 - 30.0).abs() <= 1.0);
+
+        let (ascent, height) = line_box(&spaced, scale);
+        let metrics = font.as_scaled(scale);
+        assert!((height - metrics.height() * 1.5).abs() < 1e-3);
+        assert!((ascent - metrics.ascent() - metrics.height() * 0.25).abs() < 1e-3);
+    }
+}
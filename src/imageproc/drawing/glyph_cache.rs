@@ -0,0 +1,239 @@
+use ab_glyph::{point, Font, Glyph, PxScale};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+};
+
+/// Subpixel positions per pixel, glyph positions are rounded to this grid.
+const SUBPIXELS: f32 = 4.0;
+
+/// The bytes of coverage kept before the cache is cleared.
+const CAPACITY: usize = 32 << 20;
+
+/// The rasterized coverage of a glyph, relative to its pixel-aligned origin.
+#[derive(Clone, Debug)]
+pub struct Coverage {
+    left: i32,
+    top: i32,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl Coverage {
+    fn rasterize(font: &impl Font, glyph: Glyph) -> Option<Self> {
+        let outlined = font.outline_glyph(glyph)?;
+        let bb = outlined.px_bounds();
+        let (width, height) = (bb.width() as u32, bb.height() as u32);
+        let mut data = vec![0; (width * height) as usize];
+        outlined.draw(|x, y, v| {
+            if x < width && y < height {
+                data[(y * width + x) as usize] = (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+        });
+        Some(Self {
+            left: bb.min.x as i32,
+            top: bb.min.y as i32,
+            width,
+            height,
+            data,
+        })
+    }
+
+    /// The width and height in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Call `o` with the x, y and coverage of every pixel, like [`ab_glyph::OutlinedGlyph::draw`].
+    pub fn draw(&self, mut o: impl FnMut(u32, u32, f32)) {
+        for (i, &v) in self.data.iter().enumerate() {
+            if v > 0 {
+                o(
+                    i as u32 % self.width,
+                    i as u32 / self.width,
+                    f32::from(v) / 255.0,
+                );
+            }
+        }
+    }
+}
+
+/// A stable id of a font in the glyph cache, never reused for another font.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FontId(u64);
+
+impl FontId {
+    /// A new id, different from every id created before.
+    pub fn new() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Default for FontId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Identifies a rasterized glyph: font, glyph id, scale and subpixel offset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: FontId,
+    glyph: u16,
+    scale: (u32, u32),
+    offset: (u8, u8),
+}
+
+/// Cached glyphs and the bytes of their coverage.
+#[derive(Debug, Default)]
+struct Glyphs {
+    map: HashMap<GlyphKey, Arc<Coverage>>,
+    bytes: usize,
+}
+
+/// A cache of rasterized glyphs, shared between threads.
+///
+/// Fonts are identified by a [`FontId`], which [`FontChain`](super::FontChain) gives every
+/// font it holds, so clones of a chain share cached glyphs. When the coverage of the
+/// cached glyphs exceeds 32 MiB the cache is cleared.
+#[derive(Debug, Default)]
+pub struct GlyphCache {
+    glyphs: Mutex<Glyphs>,
+}
+
+impl GlyphCache {
+    /// The cache used to draw text.
+    pub fn global() -> &'static Self {
+        static CACHE: OnceLock<GlyphCache> = OnceLock::new();
+        CACHE.get_or_init(Self::default)
+    }
+
+    /// The number of cached glyphs.
+    pub fn len(&self) -> usize {
+        self.glyphs.lock().unwrap().map.len()
+    }
+
+    /// Whether no glyph is cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every cached glyph, e.g. after dropping fonts loaded at run time.
+    pub fn clear(&self) {
+        let mut glyphs = self.glyphs.lock().unwrap();
+        glyphs.map.clear();
+        glyphs.bytes = 0;
+    }
+
+    /// The coverage of `glyph` and the pixel its coverage starts at, `None` for glyphs without outline.
+    ///
+    /// The glyph position is rounded to a quarter pixel. Glyphs of a font without `id`
+    /// are rasterized every time and not cached.
+    pub fn get(
+        &self,
+        font: &impl Font,
+        id: Option<FontId>,
+        glyph: Glyph,
+    ) -> Option<(Arc<Coverage>, i32, i32)> {
+        let PxScale { x: sx, y: sy } = glyph.scale;
+        let quantize = |v: f32| {
+            let v = (v * SUBPIXELS).round() / SUBPIXELS;
+            (v.floor(), ((v - v.floor()) * SUBPIXELS) as u8)
+        };
+        let (x, offset_x) = quantize(glyph.position.x);
+        let (y, offset_y) = quantize(glyph.position.y);
+
+        let key = id.map(|font| GlyphKey {
+            font,
+            glyph: glyph.id.0,
+            scale: (sx.to_bits(), sy.to_bits()),
+            offset: (offset_x, offset_y),
+        });
+
+        let cached = key.and_then(|key| self.glyphs.lock().unwrap().map.get(&key).cloned());
+        let coverage = match cached {
+            Some(coverage) => coverage,
+            None => {
+                // 在锁外光栅化，其他线程不必等待
+                let position = point(
+                    f32::from(offset_x) / SUBPIXELS,
+                    f32::from(offset_y) / SUBPIXELS,
+                );
+                let coverage = Arc::new(Coverage::rasterize(font, Glyph { position, ..glyph })?);
+                match key {
+                    Some(key) => self.insert(key, coverage),
+                    None => coverage,
+                }
+            }
+        };
+
+        let (left, top) = (x as i32 + coverage.left, y as i32 + coverage.top);
+        Some((coverage, left, top))
+    }
+
+    /// Cache `coverage` unless another thread was first, clearing the cache when it is full.
+    fn insert(&self, key: GlyphKey, coverage: Arc<Coverage>) -> Arc<Coverage> {
+        let mut glyphs = self.glyphs.lock().unwrap();
+        if glyphs.bytes + coverage.data.len() > CAPACITY {
+            glyphs.map.clear();
+            glyphs.bytes = 0;
+        }
+        let Glyphs { map, bytes } = &mut *glyphs;
+        map.entry(key)
+            .or_insert_with(|| {
+                *bytes += coverage.data.len();
+                coverage
+            })
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imageproc::drawing::test_font;
+
+    #[test]
+    fn test_glyph_cache() {
+        let font = test_font();
+        let id = FontId::new();
+        let cache = GlyphCache::default();
+        let glyph = |x: f32| -> Glyph {
+            font.glyph_id('a')
+                .with_scale_and_position(40.0, point(x, 30.0))
+        };
+
+        // 同一子像素位置的字形只光栅化一次
+        let (a, left_a, _) = cache.get(&font, Some(id), glyph(10.1)).unwrap();
+        let (b, left_b, _) = cache.get(&font.clone(), Some(id), glyph(20.0)).unwrap();
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(left_b - left_a, 10);
+        assert_eq!(cache.len(), 1);
+
+        // 不同的子像素位置分别缓存
+        let (c, ..) = cache.get(&font, Some(id), glyph(10.5)).unwrap();
+        assert_eq!(cache.len(), 2);
+
+        // 不同的字体即使数据地址相同也分别缓存
+        let (d, ..) = cache.get(&font, Some(FontId::new()), glyph(10.1)).unwrap();
+        assert!(!Arc::ptr_eq(&a, &d));
+        assert_eq!(cache.len(), 3);
+        let bytes = cache.glyphs.lock().unwrap().bytes;
+        assert_eq!(bytes, a.data.len() + c.data.len() + d.data.len());
+
+        // 空格没有轮廓
+        let space = font.glyph_id(' ').with_scale(40.0);
+        assert!(cache.get(&font, Some(id), space).is_none());
+
+        // 没有 id 的字体不缓存
+        cache.get(&font, None, glyph(30.0)).unwrap();
+        assert_eq!(cache.len(), 3);
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+use super::{
+    super::rect::Rect,
+    draw_text::wrap_text,
+    fonts::{line_box, Fonts},
+    shaping::{shape_text, PositionedGlyph},
+    text_style::{HAlign, VAlign},
+    wrap::fit_with,
+};
+use ab_glyph::{Font, PxScale, ScaleFont};
+
+/// A line of laid out content, such as the shaped glyphs of a [`ShapedLine`].
+///
+/// `x` is the left edge of the line and `baseline` the y position of its baseline, both
+/// relative to the top left of the [`TextLayout`] holding the line. `ascent` and `descent`
+/// are the extent of the primary font above and below the baseline, both positive.
+/// The line box is `line_height` tall and contains them.
+#[derive(Clone, Debug)]
+pub struct LineBox<T> {
+    pub content: T,
+    pub x: f32,
+    pub baseline: f32,
+    pub ascent: f32,
+    pub descent: f32,
+    pub width: f32,
+    pub line_height: f32,
+}
+
+impl<T> LineBox<T> {
+    /// The top of the line box.
+    pub fn top(&self) -> f32 {
+        self.baseline - self.ascent - (self.line_height - self.ascent - self.descent) / 2.0
+    }
+}
+
+/// A line of plain text and its shaped glyphs.
+///
+/// Glyph positions are relative to the left edge of the line and the top of its ascent.
+#[derive(Clone, Debug)]
+pub struct ShapedLine<'a, F> {
+    pub text: &'a str,
+    pub glyphs: Vec<PositionedGlyph<'a, F>>,
+}
+
+/// Lines laid out at `scale` in a `width` x `height` block whose top left is at `left`, `top`.
+///
+/// Line positions stay relative to the block, [`Self::origin`] gives where a line is drawn.
+#[derive(Clone, Debug)]
+pub struct TextLayout<T> {
+    pub scale: PxScale,
+    pub lines: Vec<LineBox<T>>,
+    pub left: f32,
+    pub top: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl<T> TextLayout<T> {
+    /// Stack `lines` top to bottom, each one line box below the previous one.
+    pub(super) fn stack(scale: PxScale, mut lines: Vec<LineBox<T>>) -> Self {
+        let mut height = 0.0;
+        for line in &mut lines {
+            line.baseline =
+                height + line.ascent + (line.line_height - line.ascent - line.descent) / 2.0;
+            height += line.line_height;
+        }
+        let width = lines.iter().map(|line| line.width).fold(0.0, f32::max);
+
+        Self {
+            scale,
+            lines,
+            left: 0.0,
+            top: 0.0,
+            width,
+            height,
+        }
+    }
+
+    /// Move the block into `rect` and align every line in it.
+    ///
+    /// Aligning again replaces the previous alignment. Justified lines are aligned left,
+    /// see [`TextStyle`](super::TextStyle) to stretch them.
+    pub fn align(&mut self, rect: Rect, h_align: HAlign, v_align: VAlign) {
+        self.left = rect.left() as f32;
+        self.top = rect.top() as f32 + v_align.offset(rect.height() as f32, self.height);
+        for line in &mut self.lines {
+            line.x = h_align.offset(rect.width() as f32, line.width);
+        }
+    }
+
+    /// The absolute position of the left edge and the baseline of `line`.
+    pub fn origin(&self, line: &LineBox<T>) -> (f32, f32) {
+        (self.left + line.x, self.top + line.baseline)
+    }
+}
+
+impl<'a, F: Font> TextLayout<ShapedLine<'a, F>> {
+    fn from_lines<T: Fonts<Font = F>>(scale: PxScale, font: &'a T, lines: Vec<&'a str>) -> Self {
+        let metrics = font.primary().as_scaled(scale);
+        let line_height = line_box(font, scale).1;
+
+        let lines = lines
+            .into_iter()
+            .map(|text| {
+                let run = shape_text(scale, font, text);
+                LineBox {
+                    content: ShapedLine {
+                        text,
+                        glyphs: run.glyphs,
+                    },
+                    x: 0.0,
+                    baseline: 0.0,
+                    ascent: metrics.ascent(),
+                    descent: -metrics.descent(),
+                    width: run.width,
+                    line_height,
+                }
+            })
+            .collect();
+        Self::stack(scale, lines)
+    }
+}
+
+/// Lay out multi-line text, wrapped to `width` if given.
+///
+/// Unlike [`text_size`](super::text_size), newlines start a new line and every line
+/// is one line height tall, so the layout measures exactly what is drawn.
+pub fn layout_text<'a, T: Fonts>(
+    scale: impl Into<PxScale> + Copy,
+    font: &'a T,
+    text: &'a str,
+    width: Option<u32>,
+) -> TextLayout<ShapedLine<'a, T::Font>> {
+    // 与自动换行一样去掉每行首尾的空白
+    let lines = match width {
+        Some(width) => wrap_text(scale, font, text, width),
+        None => text.lines().map(str::trim).collect(),
+    };
+    TextLayout::from_lines(scale.into(), font, lines)
+}
+
+/// Lay out text wrapped and shrunk to fit into a `width` x `height` box, see [`fit_text`](super::fit_text).
+///
+/// Every line is shaped once per scale tried, the layout keeps the glyphs for drawing.
+pub fn fit_layout<'a, T: Fonts>(
+    scale: impl Into<PxScale> + Copy,
+    font: &'a T,
+    text: &'a str,
+    width: u32,
+    height: u32,
+) -> TextLayout<ShapedLine<'a, T::Font>> {
+    fit_with(scale.into(), 0.0, width, height, |scale| {
+        let layout = layout_text(scale, font, text, Some(width));
+        let size = (layout.width as u32, layout.height as u32);
+        (layout, size)
+    })
+    .1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imageproc::drawing::{test_font, TextStyle};
+
+    #[test]
+    fn test_layout_text() {
+        let font = test_font();
+        let chain = TextStyle::new(40.0).line_height(1.5).fonts(&font);
+        let mut layout = layout_text(40.0, &chain, "first \n  second line", None);
+
+        // 每行高度为行高，基线间距相同
+        assert_eq!(layout.lines.len(), 2);
+        let (_, line_height) = line_box(&chain, PxScale::from(40.0));
+        let (a, b) = (&layout.lines[0], &layout.lines[1]);
+        assert!((b.baseline - a.baseline - line_height).abs() < 1e-3);
+        assert!(a.top().abs() < 1e-3);
+        assert_eq!(layout.height, line_height * 2.0);
+        assert_eq!(layout.width, b.width);
+        // 排版时整形的字形留给绘制使用
+        assert_eq!(a.content.glyphs.len(), 5);
+        assert!((a.content.glyphs[4].glyph.position.x - a.width).abs() < a.width / 2.0);
+
+        // 不带宽度时也去掉每行首尾的空白
+        assert_eq!(a.content.text, "first");
+        assert_eq!(b.content.text, "second line");
+
+        // 对齐后第二行右对齐到矩形右边，重复对齐结果不变
+        for _ in 0..2 {
+            layout.align(
+                Rect::at(10, 20).of_size(500, 300),
+                HAlign::Right,
+                VAlign::Top,
+            );
+            let (x, _) = layout.origin(&layout.lines[1]);
+            assert!((x + layout.lines[1].width - 510.0).abs() < 1e-3);
+            assert!((layout.top + layout.lines[0].top() - 20.0).abs() < 1e-3);
+        }
+    }
+}
@@ -2,13 +2,33 @@ mod canvas;
 mod draw;
 mod draw_mut;
 mod draw_text;
+mod effect;
+mod fonts;
+mod glyph_cache;
+mod layout;
+mod rich_text;
+mod shaping;
+mod text_style;
+mod vertical;
+mod wrap;
 
 #[allow(unused_imports)]
 pub use self::{
     canvas::Canvas,
     draw::Draw,
     draw_mut::DrawMut,
-    draw_text::{text_size, DrawText},
+    draw_text::{fit_scale, fit_text, text_size, wrap_text, DrawText},
+    effect::{Outline, Shadow, TextEffect},
+    fonts::{FontChain, Fonts},
+    glyph_cache::{Coverage, FontId, GlyphCache},
+    layout::{fit_layout, layout_text, LineBox, ShapedLine, TextLayout},
+    rich_text::{
+        parse_markup, plain_runs, rich_fit_scale, rich_fit_text, rich_text_overflow,
+        rich_text_size, wrap_runs, Run,
+    },
+    shaping::{shape_text, GlyphRun, PositionedGlyph},
+    text_style::{HAlign, Overflow, TextOverflow, TextStyle, VAlign},
+    vertical::{fit_vertical, vertical_columns},
 };
 
 // Set pixel at (x, y) to color if this point lies within image bounds,
@@ -21,3 +41,13 @@ where
         canvas.draw_pixel(x as u32, y as u32, color);
     }
 }
+
+/// The font file shared by the tests of the drawing modules.
+#[cfg(test)]
+const TEST_FONT: &[u8] = include_bytes!("../../MiSans-Demibold.ttf");
+
+/// The font shared by the tests of the drawing modules.
+#[cfg(test)]
+fn test_font() -> ab_glyph::FontArc {
+    ab_glyph::FontArc::try_from_slice(TEST_FONT).unwrap()
+}
@@ -0,0 +1,898 @@
+use super::{
+    super::{definitions::Clamp, rect},
+    canvas::Clipped,
+    draw_text::text_size,
+    effect::{draw_effect, TextEffect},
+    fonts::{line_box, Fonts},
+    layout::{LineBox, TextLayout},
+    shaping::shape_text,
+    text_style::{HAlign, Overflow, TextOverflow, TextStyle},
+    wrap::{break_opportunities, fit_with, wrap},
+    DrawText,
+};
+use ab_glyph::{Font as _, PxScale, ScaleFont};
+use image::{Pixel, Rgba};
+use std::ops::Range;
+
+/// A run of text drawn with a single style.
+///
+/// `size` is relative to the base scale of the whole text and
+/// `color` falls back to the base color when it is `None`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Run<P> {
+    pub text: String,
+    pub color: Option<P>,
+    pub size: f32,
+    pub bold: bool,
+}
+
+/// Parse inline markup into lines of styled runs.
+///
+/// Supported markup:
+/// - `**bold**` or `[b]bold[/b]`
+/// - `[color=#f00]red[/color]`, colors are `#rgb`, `#rrggbb` or `#rrggbbaa`
+/// - `[size=1.5]larger[/size]`, sizes are relative and nest multiplicatively
+/// - `\` escapes the next character
+///
+/// Unknown tags are kept as literal text. Spans may cross line breaks,
+/// each line is trimmed like plain text.
+#[must_use]
+pub fn parse_markup(text: &str) -> Vec<Vec<Run<Rgba<u8>>>> {
+    let mut colors: Vec<Rgba<u8>> = Vec::new();
+    let mut sizes: Vec<f32> = Vec::new();
+    let mut bold = false;
+
+    let mut lines = Vec::new();
+    for raw in text.lines() {
+        let mut runs = Vec::new();
+        let mut buf = String::new();
+        let flush =
+            |buf: &mut String, runs: &mut Vec<Run<Rgba<u8>>>, colors: &[_], sizes: &[f32], bold| {
+                if !buf.is_empty() {
+                    runs.push(Run {
+                        text: std::mem::take(buf),
+                        color: colors.last().copied(),
+                        size: sizes.iter().product(),
+                        bold,
+                    });
+                }
+            };
+
+        let mut rest = raw;
+        while let Some(c) = rest.chars().next() {
+            if c == '\\' {
+                let mut chars = rest[1..].chars();
+                if let Some(next) = chars.next() {
+                    buf.push(next);
+                }
+                rest = chars.as_str();
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix("**") {
+                flush(&mut buf, &mut runs, &colors, &sizes, bold);
+                bold = !bold;
+                rest = after;
+                continue;
+            }
+            if c == '[' {
+                if let Some(end) = rest.find(']') {
+                    let tag = &rest[1..end];
+                    let known = match tag {
+                        "b" | "/b" => {
+                            flush(&mut buf, &mut runs, &colors, &sizes, bold);
+                            bold = tag == "b";
+                            true
+                        }
+                        "/color" => {
+                            flush(&mut buf, &mut runs, &colors, &sizes, bold);
+                            colors.pop();
+                            true
+                        }
+                        "/size" => {
+                            flush(&mut buf, &mut runs, &colors, &sizes, bold);
+                            sizes.pop();
+                            true
+                        }
+                        _ => {
+                            if let Some(color) = tag.strip_prefix("color=").and_then(parse_hex) {
+                                flush(&mut buf, &mut runs, &colors, &sizes, bold);
+                                colors.push(color);
+                                true
+                            } else if let Some(size) = tag
+                                .strip_prefix("size=")
+                                .and_then(|s| s.parse::<f32>().ok())
+                                .filter(|s| *s > 0.0)
+                            {
+                                flush(&mut buf, &mut runs, &colors, &sizes, bold);
+                                sizes.push(size);
+                                true
+                            } else {
+                                false
+                            }
+                        }
+                    };
+                    if known {
+                        rest = &rest[end + 1..];
+                        continue;
+                    }
+                }
+            }
+            buf.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        flush(&mut buf, &mut runs, &colors, &sizes, bold);
+
+        // 与纯文本一致，去除每行的前后空格
+        if let Some(first) = runs.first_mut() {
+            first.text = first.text.trim_start().to_string();
+        }
+        if let Some(last) = runs.last_mut() {
+            last.text = last.text.trim_end().to_string();
+        }
+        runs.retain(|r| !r.text.is_empty());
+        lines.push(runs);
+    }
+    lines
+}
+
+/// Lines of plain text as runs without styling, markup characters are kept as literal text.
+///
+/// Lines are trimmed like in [`parse_markup`], so text without markup lays out the same.
+#[must_use]
+pub fn plain_runs<P>(text: &str) -> Vec<Vec<Run<P>>> {
+    text.lines()
+        .map(|line| {
+            let line = line.trim();
+            if line.is_empty() {
+                vec![]
+            } else {
+                vec![Run {
+                    text: line.to_string(),
+                    color: None,
+                    size: 1.0,
+                    bold: false,
+                }]
+            }
+        })
+        .collect()
+}
+
+/// Parse `#rgb`, `#rrggbb` or `#rrggbbaa`.
+fn parse_hex(s: &str) -> Option<Rgba<u8>> {
+    let hex = s.strip_prefix('#')?;
+    let digit = |i: usize, n: usize| u8::from_str_radix(hex.get(i..i + n)?, 16).ok();
+    match hex.len() {
+        3 => Some(Rgba([
+            digit(0, 1)? * 17,
+            digit(1, 1)? * 17,
+            digit(2, 1)? * 17,
+            255,
+        ])),
+        6 => Some(Rgba([digit(0, 2)?, digit(2, 2)?, digit(4, 2)?, 255])),
+        8 => Some(Rgba([
+            digit(0, 2)?,
+            digit(2, 2)?,
+            digit(4, 2)?,
+            digit(6, 2)?,
+        ])),
+        _ => None,
+    }
+}
+
+/// Horizontal offset used to synthesize bold text.
+fn bold_offset(scale: PxScale) -> f32 {
+    (scale.x / 32.0).max(1.0)
+}
+
+fn run_scale<P>(scale: PxScale, run: &Run<P>) -> PxScale {
+    PxScale {
+        x: scale.x * run.size,
+        y: scale.y * run.size,
+    }
+}
+
+/// Width, ascent and height of a line of runs.
+///
+/// An empty line takes the height of the base scale. Ascent and height
+/// include the extra line height of the font set.
+fn line_metrics<P>(scale: PxScale, font: &impl Fonts, line: &[Run<P>]) -> (f32, f32, f32) {
+    if line.is_empty() {
+        let (ascent, height) = line_box(font, scale);
+        return (0.0, ascent, height);
+    }
+    let spacing = font.letter_spacing() * scale.x;
+    let (w, a, h) = line.iter().fold((0.0, 0.0f32, 0.0f32), |(w, a, h), run| {
+        let scale = run_scale(scale, run);
+        let (ascent, height) = line_box(font, scale);
+        let mut width = text_size(scale, font, &run.text).0 as f32;
+        if run.bold {
+            width += bold_offset(scale);
+        }
+        (w + width + spacing, a.max(ascent), h.max(height))
+    });
+    // 字间距只加在字符之间
+    (w - spacing, a, h)
+}
+
+/// Get the width and height of lines of styled runs.
+pub fn rich_text_size<P>(
+    scale: impl Into<PxScale> + Copy,
+    font: &impl Fonts,
+    lines: &[Vec<Run<P>>],
+) -> (u32, u32) {
+    let (w, h) = lines.iter().fold((0f32, 0f32), |(w, h), line| {
+        let (lw, _, lh) = line_metrics(scale.into(), font, line);
+        (w.max(lw), h + lh)
+    });
+    (w as u32, h as u32)
+}
+
+/// The part of a line of runs covering `range` of its concatenated text.
+fn slice_runs<P: Copy>(line: &[Run<P>], range: Range<usize>) -> Vec<Run<P>> {
+    let mut start = 0;
+    let mut out = Vec::new();
+    for run in line {
+        let end = start + run.text.len();
+        let (a, b) = (range.start.max(start), range.end.min(end));
+        if a < b {
+            out.push(Run {
+                text: run.text[a - start..b - start].to_string(),
+                ..*run
+            });
+        }
+        start = end;
+    }
+    out
+}
+
+/// A wrapped line and whether it is the last line of its paragraph.
+type Wrapped<P> = (Vec<Run<P>>, bool);
+
+/// Wrap lines of styled runs, marking the last wrapped line of every input line.
+fn wrap_paragraphs<P: Copy>(
+    scale: PxScale,
+    font: &impl Fonts,
+    lines: &[Vec<Run<P>>],
+    width: u32,
+) -> Vec<Wrapped<P>> {
+    lines
+        .iter()
+        .flat_map(|line| {
+            let plain: String = line.iter().map(|r| r.text.as_str()).collect();
+            let ranges = wrap(&plain, width as f32, |r| {
+                line_metrics(scale, font, &slice_runs(line, r)).0
+            });
+            let last = ranges.len() - 1;
+            ranges
+                .into_iter()
+                .enumerate()
+                .map(|(i, r)| (slice_runs(line, r), i == last))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Wrap lines of styled runs into lines no wider than `width`.
+///
+/// Breaks are chosen like [`wrap_text`](super::wrap_text) on the plain text of each line.
+pub fn wrap_runs<P: Copy>(
+    scale: impl Into<PxScale> + Copy,
+    font: &impl Fonts,
+    lines: &[Vec<Run<P>>],
+    width: u32,
+) -> Vec<Vec<Run<P>>> {
+    wrap_paragraphs(scale.into(), font, lines, width)
+        .into_iter()
+        .map(|(line, _)| line)
+        .collect()
+}
+
+/// Width and height of wrapped lines.
+fn block_size<P>(scale: PxScale, font: &impl Fonts, lines: &[Wrapped<P>]) -> (f32, f32) {
+    lines.iter().fold((0f32, 0f32), |(w, h), (line, _)| {
+        let (lw, _, lh) = line_metrics(scale, font, line);
+        (w.max(lw), h + lh)
+    })
+}
+
+fn fit_paragraphs<P: Copy>(
+    scale: PxScale,
+    min_scale: f32,
+    font: &impl Fonts,
+    lines: &[Vec<Run<P>>],
+    width: u32,
+    height: u32,
+) -> (PxScale, Vec<Wrapped<P>>) {
+    fit_with(scale, min_scale, width, height, |scale| {
+        let wrapped = wrap_paragraphs(scale, font, lines, width);
+        let size = block_size(scale, font, &wrapped);
+        (wrapped, (size.0 as u32, size.1 as u32))
+    })
+}
+
+/// Wrap and shrink lines into `rect` within the scale range of `style`,
+/// with the overflow if they do not fit at its minimum scale.
+fn layout_paragraphs<P: Copy>(
+    font: &impl Fonts,
+    lines: &[Vec<Run<P>>],
+    rect: rect::Rect,
+    style: &TextStyle,
+) -> (PxScale, Vec<Wrapped<P>>, Option<TextOverflow>) {
+    let (scale, wrapped) = fit_paragraphs(
+        style.max_scale,
+        style.min_scale,
+        font,
+        lines,
+        rect.width(),
+        rect.height(),
+    );
+    let (w, h) = block_size(scale, font, &wrapped);
+    let (w, h) = (w as u32, h as u32);
+    let overflow = (w > rect.width() || h > rect.height()).then_some(TextOverflow {
+        scale: scale.y,
+        width: w,
+        height: h,
+        overflow: style.overflow,
+    });
+    (scale, wrapped, overflow)
+}
+
+/// Check whether lines of styled runs overflow a `width` x `height` box laid out by `style`.
+///
+/// Measures exactly what [`DrawText::draw_rich_text_mut`] draws, `None` if the text fits.
+pub fn rich_text_overflow<P: Copy>(
+    font: &impl Fonts,
+    lines: &[Vec<Run<P>>],
+    width: u32,
+    height: u32,
+    style: &TextStyle,
+) -> Option<TextOverflow> {
+    let rect = style.inner(rect::Rect::at(0, 0).of_size(width, height));
+    layout_paragraphs(&style.fonts(font), lines, rect, style).2
+}
+
+/// End a line with an ellipsis, dropping characters from its end until it fits `width`.
+///
+/// The cut point is found by binary search, so a long line is measured only a few times.
+fn with_ellipsis<P: Copy>(
+    scale: PxScale,
+    font: &impl Fonts,
+    line: &[Run<P>],
+    width: f32,
+) -> Vec<Run<P>> {
+    let plain: String = line.iter().map(|r| r.text.as_str()).collect();
+    let ends: Vec<_> = plain
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([plain.len()])
+        .collect();
+    let cut = |end: usize| {
+        let mut cut = slice_runs(line, 0..plain[..end].trim_end().len());
+        match cut.last_mut() {
+            Some(run) => run.text.push('…'),
+            None => {
+                let text = String::from("…");
+                cut.push(match line.first() {
+                    Some(run) => Run { text, ..*run },
+                    None => Run {
+                        text,
+                        color: None,
+                        size: 1.0,
+                        bold: false,
+                    },
+                });
+            }
+        }
+        cut
+    };
+    // 保留的字符越多行越宽，找出能放下的最长前缀，都放不下时只保留省略号
+    let fits = ends.partition_point(|&end| line_metrics(scale, font, &cut(end)).0 <= width);
+    cut(ends[fits.saturating_sub(1)])
+}
+
+/// Drop the lines below `height` and end the last remaining line with an ellipsis.
+///
+/// Lines wider than `width`, such as a single long word, are cut with an ellipsis too.
+fn ellipsize<P: Copy>(
+    scale: PxScale,
+    font: &impl Fonts,
+    lines: Vec<Wrapped<P>>,
+    width: f32,
+    height: f32,
+) -> Vec<Wrapped<P>> {
+    let total = lines.len();
+    let mut used = 0f32;
+    let mut kept = Vec::with_capacity(total);
+    for (line, last) in lines {
+        // 至少保留一行
+        let h = line_metrics(scale, font, &line).2;
+        if !kept.is_empty() && used + h > height {
+            break;
+        }
+        used += h;
+        kept.push((line, last));
+    }
+
+    let cut = kept.len() - 1;
+    let dropped = kept.len() < total;
+    kept.into_iter()
+        .enumerate()
+        .map(|(i, (line, last))| {
+            if (dropped && i == cut) || line_metrics(scale, font, &line).0 > width {
+                // 省略号所在的行不再两端对齐
+                (with_ellipsis(scale, font, &line, width), true)
+            } else {
+                (line, last)
+            }
+        })
+        .collect()
+}
+
+/// Get the scale and wrapped lines with which lines of styled runs fit into a `width` x `height` box.
+///
+/// Works like [`fit_text`](super::fit_text) for plain text.
+pub fn rich_fit_text<P: Copy>(
+    scale: impl Into<PxScale> + Copy,
+    font: &impl Fonts,
+    lines: &[Vec<Run<P>>],
+    width: u32,
+    height: u32,
+) -> (PxScale, Vec<Vec<Run<P>>>) {
+    let (scale, wrapped) = fit_paragraphs(scale.into(), 0.0, font, lines, width, height);
+    (scale, wrapped.into_iter().map(|(line, _)| line).collect())
+}
+
+/// Get the scale at which lines of styled runs fit into a `width` x `height` box.
+///
+/// Works like [`fit_scale`](super::fit_scale) for plain text.
+pub fn rich_fit_scale<P: Copy>(
+    scale: impl Into<PxScale> + Copy,
+    font: &impl Fonts,
+    lines: &[Vec<Run<P>>],
+    width: u32,
+    height: u32,
+) -> PxScale {
+    rich_fit_text(scale, font, lines, width, height).0
+}
+
+/// Split a line into pieces, marking the pieces that justified text puts extra space after.
+///
+/// Extra space goes at every break opportunity like [`wrap_text`](super::wrap_text), so Latin
+/// words are spread after whitespace and CJK text between characters, even on the same line.
+fn justify_pieces<P: Copy>(line: &[Run<P>]) -> Vec<(Run<P>, bool)> {
+    let plain: String = line.iter().map(|r| r.text.as_str()).collect();
+    let breaks = break_opportunities(&plain);
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for run in line {
+        let end = start + run.text.len();
+        let mut from = start;
+        // 在片段边界处留出额外间距，包括跨越样式的边界
+        for &b in breaks.iter().filter(|&&b| start < b && b <= end) {
+            let text = run.text[from - start..b - start].to_string();
+            pieces.push((Run { text, ..*run }, true));
+            from = b;
+        }
+        if from < end {
+            let text = run.text[from - start..].to_string();
+            pieces.push((Run { text, ..*run }, false));
+        }
+        start = end;
+    }
+    pieces
+}
+
+/// Draw the text of one run with its baseline at `baseline`.
+fn draw_run<C>(
+    canvas: &mut C,
+    color: C::Pixel,
+    (x, baseline): (f32, f32),
+    scale: PxScale,
+    font: &impl Fonts,
+    run: &Run<C::Pixel>,
+) where
+    C: DrawText,
+    <C::Pixel as Pixel>::Subpixel: Into<f32> + Clamp<f32>,
+{
+    let run_scale = run_scale(scale, run);
+    let run_color = run.color.unwrap_or(color);
+    let y = (baseline - font.primary().as_scaled(run_scale).ascent()).round() as i32;
+    canvas.draw_text_mut(run_color, x.round() as i32, y, run_scale, font, &run.text);
+    if run.bold {
+        let offset = bold_offset(run_scale);
+        canvas.draw_text_mut(
+            run_color,
+            (x + offset).round() as i32,
+            y,
+            run_scale,
+            font,
+            &run.text,
+        );
+    }
+}
+
+/// Draw the shadow and outline of one run with its baseline at `baseline`.
+fn draw_run_effect<C>(
+    canvas: &mut C,
+    (x, baseline): (f32, f32),
+    scale: PxScale,
+    font: &impl Fonts,
+    run: &Run<C::Pixel>,
+    effect: &TextEffect<C::Pixel>,
+) where
+    C: DrawText,
+    <C::Pixel as Pixel>::Subpixel: Into<f32> + Clamp<f32>,
+{
+    let run_scale = run_scale(scale, run);
+    let y = (baseline - font.primary().as_scaled(run_scale).ascent()).round() as i32;
+    let glyphs = shape_text(run_scale, font, &run.text).glyphs;
+    draw_effect(canvas, x.round() as i32, y, font, &glyphs, effect);
+}
+
+/// The width of one run as drawn, including the offset of bold text.
+fn run_width<P>(scale: PxScale, font: &impl Fonts, run: &Run<P>) -> f32 {
+    let run_scale = run_scale(scale, run);
+    let width = text_size(run_scale, font, &run.text).0 as f32;
+    if run.bold {
+        width + bold_offset(run_scale)
+    } else {
+        width
+    }
+}
+
+/// Draw lines of styled runs in `rect` laid out by `style`, wrapping and shrinking them to fit.
+///
+/// Runs on the same line share a baseline, placed below the tallest ascent of the line.
+/// Text that does not fit at the minimum scale is drawn by the overflow policy of `style`
+/// and returned.
+pub(super) fn draw_rich_text<C>(
+    canvas: &mut C,
+    color: C::Pixel,
+    rect: rect::Rect,
+    font: &impl Fonts,
+    lines: &[Vec<Run<C::Pixel>>],
+    style: &TextStyle,
+    effect: &TextEffect<C::Pixel>,
+) -> Option<TextOverflow>
+where
+    C: DrawText,
+    <C::Pixel as Pixel>::Subpixel: Into<f32> + Clamp<f32>,
+{
+    let font = &style.fonts(font);
+    let inner = style.inner(rect);
+    let (scale, lines, overflow) = layout_paragraphs(font, lines, inner, style);
+    match overflow.map(|o| o.overflow) {
+        None => draw_lines(canvas, color, inner, scale, font, &lines, style, effect),
+        Some(Overflow::Ellipsis) => {
+            let (w, h) = (inner.width() as f32, inner.height() as f32);
+            let lines = ellipsize(scale, font, lines, w, h);
+            draw_lines(canvas, color, inner, scale, font, &lines, style, effect);
+        }
+        Some(Overflow::Clip) => {
+            // 裁剪到排版所用的内边距以内，不画进相邻的文本框
+            let mut clipped = Clipped {
+                canvas,
+                rect: inner,
+            };
+            draw_lines(
+                &mut clipped,
+                color,
+                inner,
+                scale,
+                font,
+                &lines,
+                style,
+                effect,
+            );
+        }
+        Some(Overflow::Error) => {}
+    }
+    overflow
+}
+
+/// A laid out line of runs, each run with its offset from the left edge of the line.
+type RunLine<P> = Vec<(Run<P>, f32)>;
+
+/// Lay out wrapped lines as line boxes sized by the tallest run of each line.
+///
+/// Justified lines are stretched to `width`, except the last line of a paragraph.
+fn layout_runs<P: Copy>(
+    scale: PxScale,
+    font: &impl Fonts,
+    lines: &[Wrapped<P>],
+    width: f32,
+    h_align: HAlign,
+) -> TextLayout<RunLine<P>> {
+    let spacing = font.letter_spacing() * scale.x;
+    let lines = lines
+        .iter()
+        .map(|(line, last)| {
+            // 空行使用基础字号的行高
+            let size = line.iter().map(|run| run.size).reduce(f32::max);
+            let tallest = PxScale {
+                x: scale.x * size.unwrap_or(1.0),
+                y: scale.y * size.unwrap_or(1.0),
+            };
+            let metrics = font.primary().as_scaled(tallest);
+
+            // 两端对齐时段落最后一行左对齐
+            let pieces = if h_align == HAlign::Justify && !last {
+                justify_pieces(line)
+            } else {
+                line.iter().map(|run| (run.clone(), false)).collect()
+            };
+            // 按实际绘制的片段测量宽度，拉伸后最后一个片段正好到达右边
+            let widths: Vec<_> = pieces
+                .iter()
+                .map(|(run, _)| run_width(scale, font, run))
+                .collect();
+            let w = widths.iter().sum::<f32>() + spacing * (pieces.len().max(1) - 1) as f32;
+            let gaps = pieces.iter().filter(|(_, gap)| *gap).count();
+            let gap = if gaps > 0 {
+                ((width - w) / gaps as f32).max(0.0)
+            } else {
+                0.0
+            };
+            let mut x = 0.0;
+            let mut content = Vec::with_capacity(pieces.len());
+            for ((run, spread), run_w) in pieces.into_iter().zip(widths) {
+                content.push((run, x));
+                x += run_w + spacing;
+                if spread {
+                    x += gap;
+                }
+            }
+            LineBox {
+                content,
+                x: 0.0,
+                baseline: 0.0,
+                ascent: metrics.ascent(),
+                descent: -metrics.descent(),
+                width: w + gap * gaps as f32,
+                line_height: line_box(font, tallest).1,
+            }
+        })
+        .collect();
+    TextLayout::stack(scale, lines)
+}
+
+/// Draw `lines` in `rect`, the effects of the whole block first so they never cover text.
+#[allow(clippy::too_many_arguments)]
+fn draw_lines<C>(
+    canvas: &mut C,
+    color: C::Pixel,
+    rect: rect::Rect,
+    scale: PxScale,
+    font: &impl Fonts,
+    lines: &[Wrapped<C::Pixel>],
+    style: &TextStyle,
+    effect: &TextEffect<C::Pixel>,
+) where
+    C: DrawText,
+    <C::Pixel as Pixel>::Subpixel: Into<f32> + Clamp<f32>,
+{
+    let mut layout = layout_runs(scale, font, lines, rect.width() as f32, style.h_align);
+    layout.align(rect, style.h_align, style.v_align);
+    let placed = || {
+        layout.lines.iter().flat_map(|line| {
+            let (x, baseline) = layout.origin(line);
+            line.content
+                .iter()
+                .map(move |(run, dx)| (run, (x + dx, baseline)))
+        })
+    };
+    if !effect.is_none() {
+        for (run, origin) in placed() {
+            draw_run_effect(canvas, origin, scale, font, run, effect);
+        }
+    }
+    for (run, origin) in placed() {
+        draw_run(canvas, color, origin, scale, font, run);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imageproc::drawing::test_font;
+
+    #[test]
+    fn test_parse_markup() {
+        let lines = parse_markup(" a **b** [color=#f00]c[size=2]d[/size][/color] \\*e\nf");
+        let styles: Vec<_> = lines[0]
+            .iter()
+            .map(|r| (r.text.as_str(), r.color, r.size, r.bold))
+            .collect();
+        let red = Some(Rgba([255, 0, 0, 255]));
+        assert_eq!(
+            styles,
+            vec![
+                ("a ", None, 1.0, false),
+                ("b", None, 1.0, true),
+                (" ", None, 1.0, false),
+                ("c", red, 1.0, false),
+                ("d", red, 2.0, false),
+                (" *e", None, 1.0, false),
+            ]
+        );
+        assert_eq!(lines[1][0].text, "f");
+        // 未知标签保留原文
+        assert_eq!(parse_markup("[x]")[0][0].text, "[x]");
+
+        // 不解析标记时原样保留
+        let lines = plain_runs::<Rgba<u8>>(" **a** [b]\\c \n\nd");
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0][0].text, "**a** [b]\\c");
+        assert!(lines[1].is_empty());
+        assert_eq!(lines[2], parse_markup("d")[0]);
+    }
+
+    #[test]
+    fn test_justify_pieces() {
+        let pieces = |text: &str| -> Vec<(String, bool)> {
+            justify_pieces(&parse_markup(text)[0])
+                .into_iter()
+                .map(|(r, gap)| (r.text, gap))
+                .collect()
+        };
+        let owned = |v: &[(&str, bool)]| -> Vec<(String, bool)> {
+            v.iter().map(|&(t, g)| (t.to_string(), g)).collect()
+        };
+        // 样式边界处的空格不额外留出间距
+        assert_eq!(
+            pieces("a **bc** d"),
+            owned(&[("a ", true), ("bc", false), (" ", true), ("d", false)])
+        );
+        assert_eq!(
+            pieces("汉字**加粗**"),
+            owned(&[("汉", true), ("字", true), ("加", true), ("粗", false)])
+        );
+        // 同一行中的中文按字、英文按词分散
+        assert_eq!(
+            pieces("汉字 ab cd"),
+            owned(&[("汉", true), ("字 ", true), ("ab ", true), ("cd", false)])
+        );
+    }
+
+    #[test]
+    fn test_ellipsize() {
+        let font = test_font();
+        let lines = parse_markup(&"很长的标题".repeat(40));
+        let style = TextStyle::new(40.0).min_scale(20.0);
+
+        // 最小字号下仍放不下
+        let overflow = rich_text_overflow(&font, &lines, 200, 60, &style).unwrap();
+        assert!((overflow.scale - 20.0).abs() < 1e-3);
+        assert!(overflow.height > 60);
+        assert!(rich_text_overflow(&font, &parse_markup("短"), 200, 60, &style).is_none());
+
+        // 截断后只保留放得下的行，最后一行以省略号结尾
+        let scale = PxScale::from(20.0);
+        let wrapped = wrap_paragraphs(scale, &font, &lines, 200);
+        let kept = ellipsize(scale, &font, wrapped, 200.0, 60.0);
+        let (w, h) = block_size(scale, &font, &kept);
+        assert!(w <= 200.0 && h <= 60.0);
+        let (last, _) = kept.last().unwrap();
+        assert!(last.last().unwrap().text.ends_with('…'));
+    }
+
+    #[test]
+    fn test_with_ellipsis() {
+        let font = test_font();
+        let scale = PxScale::from(20.0);
+        let line = &parse_markup(&format!("**{}** tail", "abcdefgh".repeat(20)))[0];
+        let text =
+            |runs: &[Run<Rgba<u8>>]| -> String { runs.iter().map(|r| r.text.as_str()).collect() };
+
+        // 保留能放下的最长前缀，多保留一个字符就放不下
+        let cut = with_ellipsis(scale, &font, line, 150.0);
+        assert!(line_metrics(scale, &font, &cut).0 <= 150.0);
+        assert!(cut[0].bold);
+        let len = text(&cut).len() - '…'.len_utf8();
+        let mut more = slice_runs(line, 0..len + 1);
+        more.last_mut().unwrap().text.push('…');
+        assert!(line_metrics(scale, &font, &more).0 > 150.0);
+
+        // 放得下时保留整行，连省略号都放不下时只保留省略号
+        let all = with_ellipsis(scale, &font, line, f32::MAX);
+        assert_eq!(text(&all), format!("{}…", text(line)));
+        let none = with_ellipsis(scale, &font, line, 0.0);
+        assert_eq!(text(&none), "…");
+    }
+
+    #[test]
+    fn test_layout_runs() {
+        let font = test_font();
+        let scale = PxScale::from(20.0);
+        let mut lines = parse_markup("a [size=2]b[/size] c\nd e");
+        let wrapped: Vec<_> = lines.drain(..).map(|line| (line, false)).collect();
+        let layout = layout_runs(scale, &font, &wrapped, 300.0, HAlign::Justify);
+
+        // 行框按最大的字号计算，两行依次堆叠
+        let (a, b) = (&layout.lines[0], &layout.lines[1]);
+        assert_eq!(a.line_height, line_box(&font, PxScale::from(40.0)).1);
+        assert_eq!(b.line_height, line_box(&font, scale).1);
+        assert!(a.top().abs() < 1e-3);
+        assert!((b.top() - a.line_height).abs() < 1e-3);
+        assert_eq!(layout.height, a.line_height + b.line_height);
+
+        // 两端对齐的行撑满宽度，最后一个片段到达右边
+        assert!((a.width - 300.0).abs() < 1e-3);
+        let (run, x) = a.content.last().unwrap();
+        assert_eq!(run.text, "c");
+        assert!((x + run_width(scale, &font, run) - 300.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_clip_inside_padding() {
+        use super::super::super::rect::Rect;
+        use image::RgbaImage;
+
+        let font = test_font();
+        let lines = plain_runs(&"HHHH ".repeat(40));
+        let style = TextStyle::new(40.0)
+            .padding(20)
+            .min_scale(40.0)
+            .overflow(Overflow::Clip);
+        let rect = Rect::at(0, 0).of_size(200, 120);
+        let mut img = RgbaImage::from_pixel(200, 120, Rgba([0, 0, 0, 255]));
+        let white = Rgba([255, 255, 255, 255]);
+        let overflow = draw_rich_text(
+            &mut img,
+            white,
+            rect,
+            &font,
+            &lines,
+            &style,
+            &TextEffect::default(),
+        );
+
+        // 放不下的文本只画在内边距以内
+        assert!(overflow.is_some());
+        let inner = style.inner(rect);
+        let drawn: Vec<_> = img
+            .enumerate_pixels()
+            .filter(|(_, _, p)| p[0] > 0)
+            .collect();
+        assert!(!drawn.is_empty());
+        assert!(drawn.iter().all(|(x, y, _)| {
+            (inner.left()..=inner.right()).contains(&(*x as i32))
+                && (inner.top()..=inner.bottom()).contains(&(*y as i32))
+        }));
+    }
+
+    #[test]
+    fn test_effect_behind_block() {
+        use super::super::{super::rect::Rect, effect::Outline};
+        use image::RgbaImage;
+
+        let font = test_font();
+        let lines = plain_runs("HH\nHH");
+        let style = TextStyle::new(40.0);
+        let rect = Rect::at(0, 0).of_size(200, 120);
+        let white = Rgba([255, 255, 255, 255]);
+        let draw = |effect: &TextEffect<Rgba<u8>>| {
+            let mut img = RgbaImage::from_pixel(200, 120, Rgba([0, 0, 0, 255]));
+            draw_rich_text(&mut img, white, rect, &font, &lines, &style, effect);
+            img
+        };
+
+        // 描边宽于行距，第二行的描边不能盖住第一行的文字
+        let plain = draw(&TextEffect::default());
+        let outlined = draw(&TextEffect {
+            outline: Some(Outline {
+                width: 30.0,
+                color: Rgba([255, 0, 0, 255]),
+            }),
+            shadow: None,
+        });
+        let text: Vec<_> = plain
+            .enumerate_pixels()
+            .filter(|(_, _, p)| **p == white)
+            .collect();
+        assert!(!text.is_empty());
+        assert!(text
+            .iter()
+            .all(|(x, y, _)| *outlined.get_pixel(*x, *y) == white));
+    }
+}
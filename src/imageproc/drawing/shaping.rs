@@ -0,0 +1,161 @@
+use super::fonts::Fonts;
+use ab_glyph::{point, Font, Glyph, GlyphId, PxScale, ScaleFont};
+use rustybuzz::{Face, UnicodeBuffer};
+
+/// A glyph positioned in a line of text, drawn with `font`.
+///
+/// The glyph position is its origin on the baseline, relative to the top left
+/// of the line, with the baseline at the ascent of the primary font.
+#[derive(Clone, Debug)]
+pub struct PositionedGlyph<'a, F> {
+    pub font: &'a F,
+    pub glyph: Glyph,
+}
+
+/// A line of positioned glyphs and its advance width.
+#[derive(Clone, Debug)]
+pub struct GlyphRun<'a, F> {
+    pub glyphs: Vec<PositionedGlyph<'a, F>>,
+    pub width: f32,
+}
+
+/// Split `text` into byte ranges drawn with the same font.
+///
+/// Zero-width characters such as combining marks and joiners stay with the font of
+/// the previous character when that font has them, so clusters are not torn apart.
+fn font_runs<'a, T: Fonts>(font: &'a T, text: &str) -> Vec<(&'a T::Font, usize, usize)> {
+    let mut runs: Vec<(&T::Font, usize, usize)> = Vec::new();
+    for (i, c) in text.char_indices() {
+        let end = i + c.len_utf8();
+        let attached = runs.last().is_some_and(|last| {
+            let id = last.0.glyph_id(c);
+            id.0 != 0 && last.0.h_advance_unscaled(id) == 0.0
+        });
+        match runs.last_mut() {
+            Some(last) if attached => last.2 = end,
+            _ => {
+                let source = font.font_for(c);
+                match runs.last_mut() {
+                    Some(last) if std::ptr::eq(last.0, source) => last.2 = end,
+                    _ => runs.push((source, i, end)),
+                }
+            }
+        }
+    }
+    runs
+}
+
+/// Shape `text` into positioned glyphs.
+///
+/// Fonts with shaping data are shaped with `rustybuzz`, which handles ligatures,
+/// combining marks, kerning and complex scripts such as Arabic, Devanagari and Thai.
+/// Other fonts map characters to glyphs one by one, kerning neighbours from the same font.
+/// Letter spacing is added between clusters.
+pub fn shape_text<'a, T: Fonts>(
+    scale: impl Into<PxScale> + Copy,
+    font: &'a T,
+    text: &str,
+) -> GlyphRun<'a, T::Font> {
+    let scale = scale.into();
+    let ascent = font.primary().as_scaled(scale).ascent();
+    let spacing = font.letter_spacing() * scale.x;
+
+    let mut glyphs = Vec::with_capacity(text.len());
+    let mut w = 0f32;
+    let mut started = false;
+    for (source, start, end) in font_runs(font, text) {
+        let scaled = source.as_scaled(scale);
+        // 每次整形时解析字体，只读取表目录，开销很小
+        match font
+            .shaping_data(source)
+            .and_then(|data| Face::from_slice(data, 0))
+        {
+            Some(face) => {
+                let mut buffer = UnicodeBuffer::new();
+                buffer.push_str(&text[start..end]);
+                buffer.guess_segment_properties();
+                let shaped = rustybuzz::shape(&face, &[], buffer);
+
+                let (h_factor, v_factor) = (scaled.h_scale_factor(), scaled.v_scale_factor());
+                let mut last_cluster = None;
+                for (info, pos) in shaped.glyph_infos().iter().zip(shaped.glyph_positions()) {
+                    if last_cluster != Some(info.cluster) {
+                        if started {
+                            w += spacing;
+                        }
+                        started = true;
+                        last_cluster = Some(info.cluster);
+                    }
+                    let id = GlyphId(u16::try_from(info.glyph_id).unwrap_or(0));
+                    let x = w + pos.x_offset as f32 * h_factor;
+                    let y = ascent - pos.y_offset as f32 * v_factor;
+                    glyphs.push(PositionedGlyph {
+                        font: source,
+                        glyph: id.with_scale_and_position(scale, point(x, y)),
+                    });
+                    w += pos.x_advance as f32 * h_factor;
+                }
+            }
+            None => {
+                let mut last: Option<GlyphId> = None;
+                for c in text[start..end].chars() {
+                    if started {
+                        w += spacing;
+                    }
+                    started = true;
+                    let id = scaled.glyph_id(c);
+                    if let Some(last) = last {
+                        w += scaled.kern(last, id);
+                    }
+                    last = Some(id);
+                    glyphs.push(PositionedGlyph {
+                        font: source,
+                        glyph: id.with_scale_and_position(scale, point(w, ascent)),
+                    });
+                    w += scaled.h_advance(id);
+                }
+            }
+        }
+    }
+
+    GlyphRun { glyphs, width: w }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imageproc::drawing::{test_font, FontChain, TEST_FONT};
+
+    #[test]
+    fn test_shape_text() {
+        let plain = FontChain::new(test_font());
+        let shaped = FontChain::from_data(TEST_FONT).unwrap();
+
+        // 没有连字的文本两种方式的排版一致
+        let a = shape_text(40.0, &plain, "AVA text");
+        let b = shape_text(40.0, &shaped, "AVA text");
+        assert_eq!(a.glyphs.len(), b.glyphs.len());
+        assert!((a.width - b.width).abs() < 2.0);
+
+        // 组合字符与前一个字符留在同一个字体中
+        assert_eq!(font_runs(&shaped, "e\u{301}").len(), 1);
+    }
+
+    #[test]
+    fn test_combining_mark() {
+        let shaped = FontChain::from_data(TEST_FONT).unwrap();
+        let base = shape_text(40.0, &shaped, "x");
+        let run = shape_text(40.0, &shaped, "x\u{301}");
+
+        // 没有预组合字形，组合字符不占宽度，定位在基字符上方
+        assert_eq!(run.glyphs.len(), 2);
+        assert!((run.width - base.width).abs() < 1e-3);
+        let (x, mark) = (&run.glyphs[0].glyph, &run.glyphs[1].glyph);
+        assert!(mark.position.x >= x.position.x && mark.position.x < base.width);
+        let top = |g: &Glyph| {
+            let outlined = shaped.primary().outline_glyph(g.clone()).unwrap();
+            outlined.px_bounds().min.y
+        };
+        assert!(top(mark) < top(x));
+    }
+}
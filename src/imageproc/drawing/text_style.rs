@@ -0,0 +1,181 @@
+use super::{
+    super::rect::Rect,
+    fonts::{Fonts, Spaced},
+};
+use ab_glyph::PxScale;
+
+/// Horizontal alignment of lines in a text box.
+///
+/// `Justify` stretches every wrapped line to the full width, between words
+/// for Latin text and between characters for CJK text. The last line of a
+/// paragraph is aligned left.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+    Justify,
+}
+
+/// Vertical alignment of the whole text block in a text box.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VAlign {
+    Top,
+    #[default]
+    Middle,
+    Bottom,
+}
+
+/// What to do with text that does not fit its box at the minimum scale.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Drop the lines below the box and end the last line that fits with an ellipsis.
+    #[default]
+    Ellipsis,
+    /// Draw all lines, cut off at the edges of the box.
+    Clip,
+    /// Draw nothing.
+    Error,
+}
+
+/// Text that does not fit its box at the minimum scale.
+///
+/// `width` and `height` are the size the text needs at `scale`,
+/// `overflow` is the policy it was drawn with.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextOverflow {
+    pub scale: f32,
+    pub width: u32,
+    pub height: u32,
+    pub overflow: Overflow,
+}
+
+/// Layout of text in a box.
+///
+/// Text is drawn at `max_scale` and wraps to the box width. If it still does not
+/// fit, it shrinks, but never below `min_scale`, where `overflow` decides how it is drawn.
+/// Both scales shrink together, `min_scale` limits the vertical scale.
+/// `line_height` is a multiple of the height of the primary font and
+/// `letter_spacing` is the extra space between characters in ems.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    pub h_align: HAlign,
+    pub v_align: VAlign,
+    pub padding: u32,
+    pub max_scale: PxScale,
+    pub min_scale: f32,
+    pub overflow: Overflow,
+    pub line_height: f32,
+    pub letter_spacing: f32,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self::new(120.0)
+    }
+}
+
+impl TextStyle {
+    /// Centered text without padding, shrinking as far as needed.
+    #[must_use]
+    pub fn new(max_scale: impl Into<PxScale>) -> Self {
+        Self {
+            h_align: HAlign::default(),
+            v_align: VAlign::default(),
+            padding: 0,
+            max_scale: max_scale.into(),
+            min_scale: 0.0,
+            overflow: Overflow::default(),
+            line_height: 1.0,
+            letter_spacing: 0.0,
+        }
+    }
+
+    /// Set horizontal and vertical alignment.
+    #[must_use]
+    pub fn align(mut self, h_align: HAlign, v_align: VAlign) -> Self {
+        self.h_align = h_align;
+        self.v_align = v_align;
+        self
+    }
+
+    /// Set the padding on every side of the box.
+    #[must_use]
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Set the scale text never shrinks below.
+    #[must_use]
+    pub fn min_scale(mut self, min_scale: f32) -> Self {
+        self.min_scale = min_scale;
+        self
+    }
+
+    /// Set how text that does not fit at `min_scale` is drawn.
+    #[must_use]
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Set the line height as a multiple of the height of the primary font.
+    ///
+    /// # Panics
+    /// If `line_height` is not positive.
+    #[must_use]
+    pub fn line_height(mut self, line_height: f32) -> Self {
+        assert!(line_height > 0.0, "Line height must be positive.");
+        self.line_height = line_height;
+        self
+    }
+
+    /// Set the extra space between characters in ems, negative values tighten the text.
+    #[must_use]
+    pub fn letter_spacing(mut self, letter_spacing: f32) -> Self {
+        self.letter_spacing = letter_spacing;
+        self
+    }
+
+    /// `fonts` with the line height and letter spacing of this style.
+    pub(super) fn fonts<'a, F: Fonts>(&self, fonts: &'a F) -> Spaced<'a, F> {
+        Spaced {
+            fonts,
+            line_height: self.line_height,
+            letter_spacing: self.letter_spacing,
+        }
+    }
+
+    /// The part of `rect` inside the padding, at least one pixel in size.
+    pub(super) fn inner(&self, rect: Rect) -> Rect {
+        let p = self.padding.min(rect.width() / 2).min(rect.height() / 2);
+        Rect::at(rect.left() + p as i32, rect.top() + p as i32).of_size(
+            (rect.width() - p * 2).max(1),
+            (rect.height() - p * 2).max(1),
+        )
+    }
+}
+
+impl HAlign {
+    /// Offset of a line of width `used` in a box of width `total`.
+    pub(super) fn offset(self, total: f32, used: f32) -> f32 {
+        match self {
+            Self::Left | Self::Justify => 0.0,
+            Self::Center => (total - used) / 2.0,
+            Self::Right => total - used,
+        }
+    }
+}
+
+impl VAlign {
+    /// Offset of a block of height `used` in a box of height `total`.
+    pub(super) fn offset(self, total: f32, used: f32) -> f32 {
+        match self {
+            Self::Top => 0.0,
+            Self::Middle => (total - used) / 2.0,
+            Self::Bottom => total - used,
+        }
+    }
+}
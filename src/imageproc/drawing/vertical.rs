@@ -0,0 +1,240 @@
+use super::{
+    super::{definitions::Clamp, pixelops::weighted_sum, rect},
+    draw_text::{layout_glyphs, text_size},
+    fonts::{line_box, Fonts},
+    wrap::{fit_with, wrap},
+    DrawText,
+};
+use ab_glyph::{Font as _, PxScale, ScaleFont};
+use image::Pixel;
+
+/// How a character is set in a vertical column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Orientation {
+    /// CJK characters stand upright.
+    Upright,
+    /// Brackets, dashes and Latin text are rotated 90° clockwise.
+    Rotated,
+    /// Commas and full stops move to the top-right of their cell.
+    Shifted,
+}
+
+fn orientation(c: char) -> Orientation {
+    match c {
+        // 竖排专用的标点（如 '︑'）已经是竖排字形，保持直立
+        '、' | '。' | '，' | '．' => Orientation::Shifted,
+        '（' | '）' | '「' | '」' | '『' | '』' | '【' | '】' | '《' | '》' | '〈' | '〉'
+        | '〔' | '〕' | '［' | '］' | '｛' | '｝' | '〖' | '〗' | '—' | '―' | '…' | '‥' | '～'
+        | 'ー' | '–' | '：' | '；' => Orientation::Rotated,
+        _ if c.is_ascii() => Orientation::Rotated,
+        _ => Orientation::Upright,
+    }
+}
+
+/// The vertical advance of `c` in a column, including letter spacing.
+fn advance(scale: PxScale, font: &impl Fonts, c: char) -> f32 {
+    let spacing = font.letter_spacing() * scale.x;
+    spacing
+        + match orientation(c) {
+            Orientation::Rotated => {
+                let mut buf = [0; 4];
+                text_size(scale, font, c.encode_utf8(&mut buf)).0 as f32
+            }
+            _ => font.primary().as_scaled(scale).height(),
+        }
+}
+
+fn column_height(scale: PxScale, font: &impl Fonts, column: &str) -> f32 {
+    column.chars().map(|c| advance(scale, font, c)).sum()
+}
+
+/// Split the given text into vertical columns no taller than `height`.
+///
+/// Explicit newlines start a new column. Columns break like [`wrap_text`](super::wrap_text),
+/// so no column starts with closing punctuation.
+pub fn vertical_columns<'a>(
+    scale: impl Into<PxScale> + Copy,
+    font: &impl Fonts,
+    text: &'a str,
+    height: u32,
+) -> Vec<&'a str> {
+    let scale = scale.into();
+    text.lines()
+        .flat_map(|line| {
+            wrap(line, height as f32, |r| {
+                column_height(scale, font, &line[r])
+            })
+            .into_iter()
+            .map(move |r| &line[r])
+        })
+        .collect()
+}
+
+/// Get the scale and columns with which vertical text fits into a `width` x `height` box.
+///
+/// Works like [`fit_text`](super::fit_text) for horizontal text, each column is one line height wide.
+pub fn fit_vertical<'a>(
+    scale: impl Into<PxScale> + Copy,
+    font: &impl Fonts,
+    text: &'a str,
+    width: u32,
+    height: u32,
+) -> (PxScale, Vec<&'a str>) {
+    fit_with(scale.into(), 0.0, width, height, |scale| {
+        let columns = vertical_columns(scale, font, text, height);
+        let column_w = line_box(font, scale).1;
+        let w = columns.len() as f32 * column_w;
+        let h = columns
+            .iter()
+            .map(|column| column_height(scale, font, column))
+            .fold(0f32, f32::max);
+        (columns, (w as u32, h as u32))
+    })
+}
+
+/// Draw vertical text centered in `rect`, top to bottom in columns from right to left.
+pub(super) fn draw_vertical_center<C>(
+    canvas: &mut C,
+    color: C::Pixel,
+    rect: rect::Rect,
+    scale: impl Into<PxScale> + Copy,
+    font: &impl Fonts,
+    text: &str,
+) where
+    C: DrawText + ?Sized,
+    <C::Pixel as Pixel>::Subpixel: Into<f32> + Clamp<f32>,
+{
+    let (scale, columns) = fit_vertical(scale, font, text, rect.width(), rect.height());
+    // 列宽为行高，字形在列中水平居中
+    let column_w = line_box(font, scale).1;
+    let glyph_h = font.primary().as_scaled(scale).height();
+    let inset = (column_w - glyph_h) / 2.0;
+
+    // 整体居中，第一列在最右侧
+    let block_w = columns.len() as f32 * column_w;
+    let right = rect.left() as f32 + (rect.width() as f32 + block_w) / 2.0;
+
+    let mut buf = [0; 4];
+    for (i, column) in columns.iter().enumerate() {
+        let left = right - (i + 1) as f32 * column_w;
+        let mut top =
+            rect.top() as f32 + (rect.height() as f32 - column_height(scale, font, column)) / 2.0;
+        for c in column.chars() {
+            let s = c.encode_utf8(&mut buf);
+            let w = text_size(scale, font, s).0 as f32;
+            match orientation(c) {
+                Orientation::Upright => canvas.draw_text_mut(
+                    color,
+                    (left + (column_w - w) / 2.0).round() as i32,
+                    top.round() as i32,
+                    scale,
+                    font,
+                    s,
+                ),
+                Orientation::Shifted => canvas.draw_text_mut(
+                    color,
+                    (left + inset + glyph_h - w).round() as i32,
+                    (top - column_w / 2.0).round() as i32,
+                    scale,
+                    font,
+                    s,
+                ),
+                Orientation::Rotated => {
+                    draw_rotated(
+                        canvas,
+                        color,
+                        (left + inset).round() as i32,
+                        top.round() as i32,
+                        scale,
+                        font,
+                        s,
+                        glyph_h,
+                    );
+                }
+            }
+            top += advance(scale, font, c);
+        }
+    }
+}
+
+/// Draw `text` rotated 90° clockwise, the top of its line box `glyph_h` to the right of `x`.
+#[allow(clippy::too_many_arguments)]
+fn draw_rotated<C>(
+    canvas: &mut C,
+    color: C::Pixel,
+    x: i32,
+    y: i32,
+    scale: PxScale,
+    font: &impl Fonts,
+    text: &str,
+    glyph_h: f32,
+) where
+    C: DrawText + ?Sized,
+    <C::Pixel as Pixel>::Subpixel: Into<f32> + Clamp<f32>,
+{
+    let image_width = canvas.width() as i32;
+    let image_height = canvas.height() as i32;
+    let line_h = glyph_h.round() as i32;
+
+    layout_glyphs(scale, font, text, |g, left, top| {
+        g.draw(|gx, gy, gv| {
+            // 横排坐标 (px, py) 顺时针旋转后为 (line_h - 1 - py, px)
+            let px = gx as i32 + left;
+            let py = gy as i32 + top;
+            let image_x = x + line_h - 1 - py;
+            let image_y = y + px;
+
+            if (0..image_width).contains(&image_x) && (0..image_height).contains(&image_y) {
+                let image_x = image_x as u32;
+                let image_y = image_y as u32;
+                let pixel = canvas.get_pixel(image_x, image_y);
+                let weighted_color = weighted_sum(pixel, color, 1.0 - gv, gv);
+                canvas.draw_pixel(image_x, image_y, weighted_color);
+            }
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imageproc::drawing::test_font;
+
+    #[test]
+    fn test_orientation() {
+        let orientations: Vec<_> = "汉字。（A）".chars().map(orientation).collect();
+        assert_eq!(
+            orientations,
+            vec![
+                Orientation::Upright,
+                Orientation::Upright,
+                Orientation::Shifted,
+                Orientation::Rotated,
+                Orientation::Rotated,
+                Orientation::Rotated,
+            ]
+        );
+        assert_eq!(orientation('︑'), Orientation::Upright);
+    }
+
+    #[test]
+    fn test_fit_vertical() {
+        let font = test_font();
+        let scale = PxScale::from(40.0);
+        let glyph_h = font.as_scaled(scale).height();
+
+        // 每列放得下三个字，句号不能出现在列首
+        let height = (glyph_h * 3.5) as u32;
+        let (fit, columns) = fit_vertical(scale, &font, "一二三。四五\n六", 400, height);
+        assert_eq!(fit, scale);
+        assert_eq!(columns, ["一二", "三。四", "五", "六"]);
+
+        // 宽度只够两列时缩小字号，所有列都放得下
+        let (fit, columns) = fit_vertical(scale, &font, "一二三四五六七八九", 90, height);
+        assert!(fit.y < scale.y);
+        assert!(columns.len() as f32 * line_box(&font, fit).1 <= 90.0);
+        assert!(columns
+            .iter()
+            .all(|column| column_height(fit, &font, column) <= height as f32));
+    }
+}
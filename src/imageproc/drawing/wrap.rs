@@ -0,0 +1,161 @@
+use ab_glyph::PxScale;
+use std::ops::Range;
+
+/// Characters that must not start a line (kinsoku).
+const NO_START: &str = "、。，．,.：:；;！!？?）)」』】》〉〕］]｝}’”〗〙〛…‥ー～・々ゝゞヽヾぁぃぅぇぉっゃゅょゎァィゥェォッャュョヮヵヶ%％";
+
+/// Characters that must not end a line (kinsoku).
+const NO_END: &str = "（(「『【《〈〔［[｛{‘“〖〘〚";
+
+/// Whether `c` is wide text that may be broken between any two characters.
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{1100}'..='\u{11FF}'
+        | '\u{2E80}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FE30}'..='\u{FE4F}'
+        | '\u{FF00}'..='\u{FFEF}'
+        | '\u{20000}'..='\u{2FA1F}')
+}
+
+/// Byte offsets in `text` where a line break is allowed.
+///
+/// Latin text breaks after whitespace, CJK text breaks between characters,
+/// except before closing punctuation or after opening punctuation.
+pub(super) fn break_opportunities(text: &str) -> Vec<usize> {
+    let mut breaks = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, p)) = chars.next() {
+        let Some(&(i, c)) = chars.peek() else {
+            break;
+        };
+        let allowed = if p.is_whitespace() {
+            !c.is_whitespace()
+        } else {
+            is_cjk(p) || is_cjk(c)
+        };
+        if allowed && !c.is_whitespace() && !NO_START.contains(c) && !NO_END.contains(p) {
+            breaks.push(i);
+        }
+    }
+    breaks
+}
+
+/// Shrink `range` of `text` so it does not start or end with whitespace.
+fn trim(text: &str, range: Range<usize>) -> Range<usize> {
+    let s = &text[range.clone()];
+    let start = range.start + (s.len() - s.trim_start().len());
+    let end = range.end - (s.len() - s.trim_end().len());
+    start..end.max(start)
+}
+
+/// Greedily wrap a single line of `text` into ranges no wider than `width`.
+///
+/// `measure` returns the width of a range of `text`. It is only called on the
+/// pieces between two break opportunities, the width of a line is the sum of
+/// its pieces, so each piece is measured once. A word wider than `width` on
+/// its own is kept on one line and overflows.
+pub(super) fn wrap(
+    text: &str,
+    width: f32,
+    mut measure: impl FnMut(Range<usize>) -> f32,
+) -> Vec<Range<usize>> {
+    let mut out = Vec::new();
+    // 当前行起点、当前行已放下的宽度以及上一个断点
+    let (mut start, mut used, mut last) = (0, 0.0, 0);
+    for b in break_opportunities(text)
+        .into_iter()
+        .chain(std::iter::once(text.len()))
+    {
+        let piece = trim(text, last..b);
+        let tail = measure(piece.clone());
+        if used + tail > width && last > start {
+            out.push(trim(text, start..last));
+            start = last;
+            used = 0.0;
+        }
+        // 断点前的空白只在行中计入宽度
+        used += if piece.end == b {
+            tail
+        } else {
+            measure(piece.start..b)
+        };
+        last = b;
+    }
+    out.push(trim(text, start..text.len()));
+    out
+}
+
+/// Find the largest scale, no larger than `scale` and no smaller than `min`,
+/// at which `layout` fits into a `width` x `height` box.
+///
+/// `layout` reflows the text at the given scale and returns it with its size.
+/// Because the text reflows, the scale is shrunk in small steps. At `min`
+/// the text is returned even if it does not fit.
+pub(super) fn fit_with<T>(
+    scale: PxScale,
+    min: f32,
+    width: u32,
+    height: u32,
+    mut layout: impl FnMut(PxScale) -> (T, (u32, u32)),
+) -> (PxScale, T) {
+    let min = min.max(1.0);
+    let mut scale = scale;
+    loop {
+        let (laid, (w, h)) = layout(scale);
+        if (w <= width && h <= height) || scale.y <= min {
+            return (scale, laid);
+        }
+        // 溢出越多缩小越快，接近时每次缩小 5%
+        let ratio = (width as f32 / w as f32).min(height as f32 / h as f32);
+        let step = ratio.sqrt().min(0.95).max(min / scale.y);
+        scale = PxScale {
+            x: scale.x * step,
+            y: scale.y * step,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        let lines = |text: &str, width: f32| -> Vec<String> {
+            wrap(text, width, |r| text[r].chars().count() as f32)
+                .into_iter()
+                .map(|r| text[r].to_string())
+                .collect()
+        };
+        assert_eq!(
+            lines("the quick brown fox", 10.0),
+            ["the quick", "brown fox"]
+        );
+        assert_eq!(lines("extraordinary", 5.0), ["extraordinary"]);
+        assert_eq!(
+            lines("a bb ccc dddd eeeee ffffff", 9.0),
+            ["a bb ccc", "dddd", "eeeee", "ffffff"]
+        );
+        // 句号不能出现在行首，左括号不能出现在行尾
+        assert_eq!(
+            lines("一二三四。五（六）", 4.0),
+            ["一二三", "四。五", "（六）"]
+        );
+    }
+
+    #[test]
+    fn test_wrap_measures_pieces_once() {
+        let text = "很长的标题".repeat(20);
+        let mut measured = 0;
+        let ranges = wrap(&text, 12.0, |r| {
+            measured += 1;
+            text[r].chars().count() as f32
+        });
+        // 每个断点之间的片段只量一次，行宽累加得到
+        assert_eq!(measured, 100);
+        assert_eq!(ranges.len(), 9);
+        assert!(ranges.iter().all(|r| text[r.clone()].chars().count() <= 12));
+    }
+}
@@ -8,7 +8,7 @@ pub mod swiping_img;
 use error::Result;
 use prelude::{debug_print, read_json};
 use std::{fs, path::Path, time::Instant};
-use swiping_img::BigImg;
+use swiping_img::{BigImg, OutputTarget, Query};
 
 fn main() -> Result<()> {
     let t = Instant::now();
@@ -17,14 +17,24 @@ fn main() -> Result<()> {
     let work_dir = Path::new("D:/pictures/arknights/0birth");
     fs::create_dir_all(work_dir).map_err(|e| err_new_io!(e))?;
 
-    let data_use = &read_json(data_file)?[..5];
+    // 如 `--sort text_down --reverse --limit 10`，默认与之前一样只取前 5 个，`--limit all` 取全部
+    let args = ["--limit", "5"]
+        .map(String::from)
+        .into_iter()
+        .chain(std::env::args().skip(1));
+    let query = Query::parse_args(args)?;
+    let data_use = query.apply(read_json(data_file)?);
 
     // let si = BigImg::new(work_dir, &data_use);
     let mut si = BigImg::new_with_default(work_dir, &data_use);
     si.text_background_color([236, 162, 56, 255], [255, 226, 197, 255])
         .text_color([0, 0, 0, 255]);
     debug_print(&si);
-    si.run("result.mp4")?;
+    let report = si.run(&[OutputTarget::new("result.mp4", (1920, 1080), 60)])?;
+    // 只剩不影响渲染的警告
+    if !report.is_empty() {
+        println!("{report}");
+    }
 
     println!("cost {} s", t.elapsed().as_secs());
     Ok(())
@@ -4,7 +4,7 @@ pub mod simple_rng;
 use crate::{
     err_new, err_new_io,
     error::{Kind, Result},
-    prelude::RESOURCE,
+    prelude::{self, RESOURCE},
     swiping_img::Chunk,
 };
 use serde::Serialize;
@@ -52,14 +52,11 @@ where
     Ok(info)
 }
 
-fn write_json<P: AsRef<Path>, T: Serialize>(save_name: P, data: &T) -> Result<()> {
-    let mut data_path = PathBuf::from(DATA_PATH);
+/// 将图像块写入数据目录，图片路径尽量保存为相对于数据目录的路径
+fn write_json<P: AsRef<Path>>(save_name: P, data: &[Chunk]) -> Result<()> {
+    let data_path = PathBuf::from(DATA_PATH);
     fs::create_dir_all(&data_path).map_err(|e| err_new_io!(e))?;
-    data_path.push(save_name.as_ref());
-    let writer = File::create(data_path).map_err(|e| err_new_io!(e))?;
-    serde_json::to_writer_pretty(writer, data)
-        .map_err(|e| err_new!(Kind::Other, &e.to_string()))?;
-    Ok(())
+    prelude::write_json(data_path.join(save_name.as_ref()), data, true)
 }
 
 pub fn no_skin() -> Result<()> {
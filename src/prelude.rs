@@ -3,7 +3,12 @@ use crate::{
     error::{Kind, Result},
     {err_new, err_new_io},
 };
-use std::{collections::HashMap, fmt::Debug, fs::File, path::Path};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs::File,
+    path::{Component, Path, PathBuf},
+};
 
 pub const RESOURCE: &str = "../resources";
 
@@ -12,13 +17,116 @@ pub fn debug_print<T: Debug>(s: T) {
     dbg!(s);
 }
 
+/// 引用了资源文件的数据，如图像块中的图片和字体
+///
+/// 读写 JSON 时通过它在相对路径和绝对路径之间转换，使数据集可以在机器之间移动。
+pub trait AssetPaths {
+    /// 所有资源文件路径的可变引用
+    fn asset_paths_mut(&mut self) -> Vec<&mut PathBuf>;
+}
+
+/// 读取 JSON 数据，相对路径相对于 JSON 文件所在目录解析
+///
+/// # Errors
+/// - 如果文件打开失败或不是有效的 JSON，则返回 `Err`。
+///
 pub fn read_json<P, T>(file: P) -> Result<Vec<T>>
 where
     P: AsRef<Path>,
-    T: serde::Serialize + serde::de::DeserializeOwned,
+    T: serde::de::DeserializeOwned + AssetPaths,
+{
+    let file = file.as_ref();
+    read_json_with_root(file, file.parent().unwrap_or(Path::new("")))
+}
+
+/// 读取 JSON 数据，相对路径相对于资源根目录 `root` 解析
+///
+/// # Errors
+/// - 如果文件打开失败或不是有效的 JSON，则返回 `Err`。
+///
+pub fn read_json_with_root<P, R, T>(file: P, root: R) -> Result<Vec<T>>
+where
+    P: AsRef<Path>,
+    R: AsRef<Path>,
+    T: serde::de::DeserializeOwned + AssetPaths,
 {
     let file = File::open(file.as_ref()).map_err(|e| err_new_io!(e))?;
-    Ok(serde_json::from_reader(file).map_err(|e| err_new!(Kind::Other, &e.to_string()))?)
+    let mut data: Vec<T> =
+        serde_json::from_reader(file).map_err(|e| err_new!(Kind::Other, &e.to_string()))?;
+    for item in &mut data {
+        for path in item.asset_paths_mut() {
+            if path.is_relative() {
+                *path = root.as_ref().join(&*path);
+            }
+        }
+    }
+    Ok(data)
+}
+
+/// 写入 JSON 数据
+///
+/// `portable` 为 `true` 时，资源路径改写为相对于 JSON 文件所在目录的路径，
+/// 无法表示为相对路径的（如位于其他盘符）保持不变。
+///
+/// # Errors
+/// - 如果文件创建或写入失败，则返回 `Err`。
+///
+pub fn write_json<P, T>(file: P, data: &[T], portable: bool) -> Result<()>
+where
+    P: AsRef<Path>,
+    T: serde::Serialize + Clone + AssetPaths,
+{
+    let file = file.as_ref();
+    let mut data = data.to_vec();
+    if portable {
+        let dir = file.parent().unwrap_or(Path::new(""));
+        for item in &mut data {
+            for path in item.asset_paths_mut() {
+                if let Some(relative) = relative_to(path, dir) {
+                    *path = relative;
+                }
+            }
+        }
+    }
+    let writer = File::create(file).map_err(|e| err_new_io!(e))?;
+    serde_json::to_writer_pretty(writer, &data).map_err(|e| err_new!(Kind::Other, &e.to_string()))
+}
+
+/// 计算 `path` 相对于目录 `base` 的路径，使用 `/` 分隔
+///
+/// 两者不在同一根目录或盘符下时返回 `None`。
+pub fn relative_to(path: &Path, base: &Path) -> Option<PathBuf> {
+    let path = std::path::absolute(path).ok()?;
+    let base = std::path::absolute(base).ok()?;
+    let mut path = path.components().peekable();
+    let mut base = base.components().peekable();
+    if path.peek() != base.peek() {
+        return None;
+    }
+    while let (Some(a), Some(b)) = (path.peek(), base.peek()) {
+        if a != b {
+            break;
+        }
+        path.next();
+        base.next();
+    }
+
+    let mut parts = Vec::new();
+    for c in base {
+        match c {
+            Component::Normal(_) => parts.push(String::from("..")),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    for c in path {
+        match c {
+            Component::Normal(name) => parts.push(name.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(PathBuf::from(parts.join("/")))
 }
 
 #[cfg(test)]
@@ -29,4 +137,16 @@ mod tests {
     fn test_name() {
         println!("{:?}", std::fs::canonicalize(RESOURCE));
     }
+
+    #[test]
+    fn test_relative_to() {
+        assert_eq!(
+            relative_to(Path::new("/a/b/c.png"), Path::new("/a/data")),
+            Some(PathBuf::from("../b/c.png"))
+        );
+        assert_eq!(
+            relative_to(Path::new("/a/data/c.png"), Path::new("/a/data")),
+            Some(PathBuf::from("c.png"))
+        );
+    }
 }
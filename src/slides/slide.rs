@@ -1,12 +1,11 @@
 use crate::{
-    err_new_image,
-    error::Result,
+    err_new, err_new_image,
+    error::{Kind, Result},
     imageproc::{
-        drawing::{DrawMut, DrawText},
+        drawing::{DrawMut, DrawText, FontChain, Overflow, TextEffect, TextStyle},
         rect::Rect,
     },
 };
-use ab_glyph::FontArc;
 use image::{DynamicImage, GenericImage, GenericImageView, Rgba};
 use std::path::PathBuf;
 
@@ -15,10 +14,11 @@ pub enum ContentType {
     Image(PathBuf),
     Text {
         content: String,
-        max_scale: f32,
+        style: TextStyle,
         color: Rgba<u8>,
         background_color: Option<Rgba<u8>>,
-        font: FontArc,
+        font: FontChain,
+        effect: TextEffect<Rgba<u8>>,
     },
 }
 
@@ -40,11 +40,12 @@ impl Default for Slide {
             elements: vec![SlideElement {
                 content: ContentType::Text {
                     content: "default".to_string(),
-                    max_scale: 100.0,
+                    style: TextStyle::new(100.0),
                     color: [255, 0, 0, 255].into(),
                     background_color: None,
-                    font: FontArc::try_from_slice(include_bytes!("../MiSans-Demibold.ttf"))
+                    font: FontChain::from_data(include_bytes!("../MiSans-Demibold.ttf").as_slice())
                         .unwrap(),
+                    effect: TextEffect::default(),
                 },
                 position: Rect::at(0, 0).of_size(480, 500),
             }],
@@ -75,15 +76,22 @@ pub fn render_frame(slide: &Slide) -> Result<DynamicImage> {
             }
             ContentType::Text {
                 ref content,
-                max_scale,
+                ref style,
                 color,
                 background_color,
                 ref font,
+                ref effect,
             } => {
                 if let Some(background_color) = background_color {
                     img.draw_filled_rounded_rect_mut(rect, 10, background_color);
                 }
-                img.draw_text_center_mut(color, rect, max_scale, &font, &content);
+                let overflow = img.draw_text_styled_mut(color, rect, font, content, style, effect);
+                if let Some(o) = overflow.filter(|o| o.overflow == Overflow::Error) {
+                    return Err(err_new!(
+                        Kind::TextOverflow,
+                        &format!("text {content:?} overflows its box at {:.1}px", o.scale)
+                    ));
+                }
             }
         }
     }
@@ -100,7 +108,8 @@ mod tests {
     use super::*;
     #[test]
     fn test_name() {
-        let font = FontArc::try_from_slice(include_bytes!("../MiSans-Demibold.ttf")).unwrap();
+        let font =
+            FontChain::from_data(include_bytes!("../MiSans-Demibold.ttf").as_slice()).unwrap();
         let slides = Slide {
             elements: vec![
                 SlideElement {
@@ -110,20 +119,22 @@ mod tests {
                 SlideElement {
                     content: ContentType::Text {
                         content: "this is test 1".to_string(),
-                        max_scale: 100.0,
+                        style: TextStyle::new(100.0),
                         color: [255, 0, 0, 255].into(),
                         background_color: Some([23, 150, 235, 255].into()),
                         font: font.clone(),
+                        effect: TextEffect::default(),
                     },
                     position: Rect::at(10, 600).of_size(460, 150),
                 },
                 SlideElement {
                     content: ContentType::Text {
                         content: "oh!\nthis is test 2".to_string(),
-                        max_scale: 100.0,
+                        style: TextStyle::new(100.0),
                         color: [0, 255, 0, 255].into(),
                         background_color: Some([44, 85, 153, 255].into()),
                         font: font.clone(),
+                        effect: TextEffect::default(),
                     },
                     position: Rect::at(10, 800).of_size(460, 150),
                 },
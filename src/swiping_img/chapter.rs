@@ -0,0 +1,148 @@
+use super::BigImg;
+use std::fmt::Write;
+
+/// 视频章节
+///
+/// # Parameters
+///
+/// * `start`: 开始时间（秒）。
+/// * `end`: 结束时间（秒）。
+/// * `title`: 章节标题。
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chapter {
+    pub start: f32,
+    pub end: f32,
+    pub title: String,
+}
+
+/// 转义 `FFMETADATA` 中的特殊字符
+fn escape(text: &str) -> String {
+    text.chars()
+        .fold(String::with_capacity(text.len()), |mut out, c| {
+            if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+                out.push('\\');
+            }
+            out.push(c);
+            out
+        })
+}
+
+/// 生成 `FFmpeg` 的 `FFMETADATA` 章节文件内容，时间以毫秒为单位
+pub(crate) fn ffmetadata(chapters: &[Chapter]) -> String {
+    let mut out = String::from(";FFMETADATA1\n");
+    for chapter in chapters {
+        let _ = write!(
+            out,
+            "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+            (chapter.start * 1000.0).round() as u64,
+            (chapter.end * 1000.0).round() as u64,
+            escape(&chapter.title)
+        );
+    }
+    out
+}
+
+impl BigImg<'_> {
+    /// 分组分隔块对应的章节
+    ///
+    /// 章节从分隔块左边缘滑到屏幕中央时开始，到下一个分隔块开始时结束，
+    /// 最后一个章节到视频结束。
+    ///
+    /// # Results
+    /// 返回章节列表，没有分隔块时为空。
+    ///
+    #[must_use]
+    pub fn chapters(&self) -> Vec<Chapter> {
+        let offsets = self.offsets(self.chunks);
+        let starts: Vec<(f32, &str)> = self
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.is_divider())
+            .map(|(i, c)| (self.centre_time(offsets[i]), c.title()))
+            .collect();
+
+        let duration = self.duration();
+        starts
+            .iter()
+            .enumerate()
+            .map(|(k, &(start, title))| Chapter {
+                start,
+                end: starts.get(k + 1).map_or(duration, |s| s.0),
+                title: title.to_string(),
+            })
+            .collect()
+    }
+
+    /// 长图中横坐标 `edge` 滑到屏幕中央时在整个视频中的时间（秒）
+    ///
+    /// 开始时已在屏幕中央左侧的为 0，即从封面开始；滑不到屏幕中央的为滚动结束的时间。
+    pub(super) fn centre_time(&self, edge: u32) -> f32 {
+        let x = edge.saturating_sub(self.screen.0 / 2).min(self.max_x());
+        if x == 0 {
+            0.0
+        } else {
+            self.time_at(x)
+        }
+    }
+
+    /// 屏幕左边缘滑到 `x` 时在整个视频中的时间（秒）
+    fn time_at(&self, x: u32) -> f32 {
+        let offsets = self.offsets(self.chunks);
+        let speed = self.speed();
+        let mut elapsed = self.video_cover_time as f32;
+        for segment in self.divide() {
+            let x0 = offsets[segment.range.start];
+            let scroll = self.scroll(&segment, &offsets);
+            if x < x0 + scroll || segment.next.is_none() {
+                return elapsed + x.saturating_sub(x0).min(scroll) as f32 / speed;
+            }
+            elapsed += self.frames_to_seconds(self.scroll_frames(scroll));
+        }
+        elapsed
+    }
+
+    /// 整个视频的时长（秒）
+    pub(super) fn duration(&self) -> f32 {
+        let offsets = self.offsets(self.chunks);
+        let scroll = self
+            .divide()
+            .iter()
+            .map(|segment| self.scroll_frames(self.scroll(segment, &offsets)))
+            .sum();
+        (self.video_cover_time + self.video_ending_time) as f32 + self.frames_to_seconds(scroll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swiping_img::Chunk;
+    use std::path::Path;
+
+    #[test]
+    fn test_chapters() {
+        let chunk =
+            |name: &str| Chunk::from_source(Path::new("a.png"), vec![name.to_string()], vec![]);
+        let chunks = vec![
+            Chunk::divider("A"),
+            chunk("a1"),
+            chunk("a2"),
+            chunk("a3"),
+            chunk("a4"),
+            Chunk::divider("B=1"),
+            chunk("b1"),
+            chunk("b2"),
+            chunk("b3"),
+            chunk("b4"),
+        ];
+        let si = BigImg::new_with_default(Path::new("."), &chunks);
+        let chapters = si.chapters();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].start, 0.0);
+        // 第二个分隔块在 2400 像素处，滑到屏幕中央需要 (2400 - 960) / 160 秒
+        assert_eq!(chapters[1].start, 3.0 + 9.0);
+        assert_eq!(chapters[0].end, chapters[1].start);
+        assert!(ffmetadata(&chapters).contains("title=B\\=1\n"));
+    }
+}
@@ -1,37 +1,121 @@
-use super::BigImg;
+use super::{
+    gallery::{self, GalleryLayout},
+    section::{self, Section},
+    source::ImageSource,
+    style::{ChunkStyle, Style},
+    BigImg,
+};
 use crate::{
     err_new, err_new_image,
     error::{Kind, Result},
     imageproc::{
-        drawing::{DrawMut, DrawText},
+        drawing::{DrawMut, DrawText, Overflow, TextOverflow, TextStyle},
         rect::Rect,
     },
+    prelude::AssetPaths,
 };
-use image::{DynamicImage, GenericImage, GenericImageView};
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use image::{DynamicImage, GenericImage};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
 
 // 定义 Chunk 结构体
-#[derive(Serialize, Deserialize)]
+//
+// `pic_path` 为主图片，可以是文件路径、内存中的图片或 JSON 中嵌入的 base64 数据；
+// `gallery` 为 `pic_path` 之后的其余图片，按 `layout` 在图片区域中排列；
+// `width` 为宽度倍数，实际宽度为 `BigImg` 的 `width_chunk` 乘以该倍数；
+// `text_up` 和 `text_down` 为默认布局上下两个文本区域的文本，与旧数据格式相同；
+// `sections` 为其他文本区域的文本，以区域名称为键，未知的字段不会被当作文本区域；
+// `style` 只覆盖该图像块的样式；
+// `metadata` 为不绘制的附加字段，如日期和星级，供查询筛选和排序
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Chunk {
-    pic_path: PathBuf,
+    pic_path: ImageSource,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    gallery: Vec<ImageSource>,
+    #[serde(default, skip_serializing_if = "GalleryLayout::is_default")]
+    layout: GalleryLayout,
+    #[serde(
+        default = "default_width",
+        deserialize_with = "positive_width",
+        skip_serializing_if = "is_default_width"
+    )]
+    width: f32,
+    #[serde(default, skip_serializing_if = "ChunkStyle::is_empty")]
+    style: ChunkStyle,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
     text_up: Vec<String>,
+    #[serde(default)]
     text_down: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    sections: BTreeMap<String, Vec<String>>,
+}
+
+fn default_width() -> f32 {
+    1.0
+}
+
+/// 与 [`Chunk::with_width`] 一致，拒绝不是正数的宽度倍数
+fn positive_width<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<f32, D::Error> {
+    let width = f32::deserialize(deserializer)?;
+    if width > 0.0 && width.is_finite() {
+        Ok(width)
+    } else {
+        Err(de::Error::custom(format!(
+            "chunk width must be positive, got {width}"
+        )))
+    }
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_default_width(width: &f32) -> bool {
+    (*width - default_width()).abs() < f32::EPSILON
+}
+
+/// 文本区域的背景框及其中每行文本的位置
+pub(crate) struct TextBox<'a> {
+    pub(crate) index: usize,
+    pub(crate) section: &'a Section,
+    pub(crate) rect: Rect,
+    pub(crate) lines: Vec<(Rect, &'a str)>,
+}
+
+// 图片和样式中的字体都是资源文件
+impl AssetPaths for Chunk {
+    fn asset_paths_mut(&mut self) -> Vec<&mut PathBuf> {
+        std::iter::once(&mut self.pic_path)
+            .chain(&mut self.gallery)
+            .filter_map(ImageSource::path_mut)
+            .chain(self.style.font_mut())
+            .collect()
+    }
 }
 
 // 实现 Chunk 结构体的 Debug trait
 impl std::fmt::Debug for Chunk {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Chunk")
-            .field("pic_path", &self.pic_path.to_str())
+            .field("pic_path", &self.pic_path)
+            .field("gallery", &self.gallery)
+            .field("layout", &self.layout)
+            .field("width", &self.width)
+            .field("style", &self.style)
+            .field("metadata", &self.metadata)
             .field("text_up", &self.text_up)
             .field("text_down", &self.text_down)
+            .field("sections", &self.sections)
             .finish()
     }
 }
 
 impl Chunk {
-    /// 创建一个新的 Chunk 实例
+    /// 标记分组分隔块的附加字段
+    pub const DIVIDER: &'static str = "divider";
+
+    /// 创建一个新的 Chunk 实例，文本放入默认布局的上下两个区域
     ///
     /// # Parameters
     ///
@@ -53,11 +137,247 @@ impl Chunk {
                 "Invalid path"
             ));
         }
-        Ok(Chunk {
-            pic_path,
+        Ok(Self::from_source(pic_path, text_up, text_down))
+    }
+
+    /// 使用任意图片来源创建 Chunk 实例，文本放入默认布局的上下两个区域
+    ///
+    /// 与 [`Chunk::new`] 不同，不检查文件路径是否存在，
+    /// 适用于程序生成的图片或已读入内存的图片数据。
+    ///
+    /// # Parameters
+    ///
+    /// * `source` - 图片来源，如 `DynamicImage`、编码后的图片数据或文件路径
+    /// * `text_up` - 上方文本的向量
+    /// * `text_down` - 下方文本的向量
+    pub fn from_source(
+        source: impl Into<ImageSource>,
+        text_up: Vec<String>,
+        text_down: Vec<String>,
+    ) -> Self {
+        Chunk {
+            pic_path: source.into(),
+            gallery: Vec::new(),
+            layout: GalleryLayout::default(),
+            width: default_width(),
+            style: ChunkStyle::default(),
+            metadata: BTreeMap::new(),
             text_up,
             text_down,
-        })
+            sections: BTreeMap::new(),
+        }
+    }
+
+    /// 设置某个文本区域的文本
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - 文本区域名称，默认布局的两个区域写入 `text_up` 和 `text_down`，其余写入 `sections`
+    /// * `lines` - 该区域中逐行绘制的文本
+    #[must_use]
+    pub fn with_text(mut self, name: impl Into<String>, lines: Vec<String>) -> Self {
+        let name = name.into();
+        match name.as_str() {
+            Section::UP => self.text_up = lines,
+            Section::DOWN => self.text_down = lines,
+            _ => {
+                self.sections.insert(name, lines);
+            }
+        }
+        self
+    }
+
+    /// 某个文本区域的文本，没有时为空
+    #[must_use]
+    pub fn text(&self, name: &str) -> &[String] {
+        match name {
+            Section::UP => &self.text_up,
+            Section::DOWN => &self.text_down,
+            _ => self.sections.get(name).map_or(&[], Vec::as_slice),
+        }
+    }
+
+    /// 创建分组分隔块
+    ///
+    /// 分隔块没有图片，分组标题绘制在整个图像块中，标题放在上方文本区域。
+    ///
+    /// # Parameters
+    ///
+    /// * `title` - 分组标题
+    #[must_use]
+    pub fn divider(title: impl Into<String>) -> Self {
+        Self::from_source(ImageSource::Blank, vec![title.into()], Vec::new())
+            .with_metadata(Self::DIVIDER, true)
+    }
+
+    /// 是否为分组分隔块
+    #[must_use]
+    pub fn is_divider(&self) -> bool {
+        self.metadata(Self::DIVIDER) == Some(&serde_json::Value::Bool(true))
+    }
+
+    /// 设置附加字段
+    ///
+    /// # Parameters
+    ///
+    /// * `key` - 字段名
+    /// * `value` - 字段值，如字符串或数字
+    #[must_use]
+    pub fn with_metadata(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// 附加字段的值
+    #[must_use]
+    pub fn metadata(&self, key: &str) -> Option<&serde_json::Value> {
+        self.metadata.get(key)
+    }
+
+    /// 设置该图像块的样式覆盖
+    ///
+    /// # Parameters
+    ///
+    /// * `style` - 样式覆盖，未设置的字段沿用 `BigImg` 的默认样式
+    #[must_use]
+    pub fn with_style(mut self, style: ChunkStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// 为图像块添加更多图片
+    ///
+    /// # Parameters
+    ///
+    /// * `gallery` - 其余图片的来源，如文件路径或内存中的图片
+    /// * `layout` - 多张图片的布局方式
+    ///
+    /// # Errors
+    ///
+    /// * `IoError(NotFound)` - 如果提供的图片路径无效或不存在
+    pub fn with_gallery<I: Into<ImageSource>>(
+        mut self,
+        gallery: Vec<I>,
+        layout: GalleryLayout,
+    ) -> Result<Self> {
+        let gallery: Vec<ImageSource> = gallery.into_iter().map(Into::into).collect();
+        if gallery.iter().any(|p| !p.exists()) {
+            return Err(err_new!(
+                Kind::IoError(std::io::ErrorKind::NotFound),
+                "Invalid path"
+            ));
+        }
+        self.gallery = gallery;
+        self.layout = layout;
+        Ok(self)
+    }
+
+    /// 设置宽度倍数
+    ///
+    /// # Parameters
+    ///
+    /// * `width` - 宽度倍数，如 `2.0` 为双倍宽度的重点图像块，`0.25` 为窄分隔块
+    ///
+    /// # Panics
+    ///
+    /// * 如果 `width` 不是正数，程序将 panic
+    #[must_use]
+    pub fn with_width(mut self, width: f32) -> Self {
+        assert!(width > 0.0, "Chunk width must be positive.");
+        self.width = width;
+        self
+    }
+
+    /// 宽度倍数
+    #[must_use]
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// 该图像块的样式覆盖
+    #[must_use]
+    pub fn style(&self) -> &ChunkStyle {
+        &self.style
+    }
+
+    /// 多张图片的布局方式
+    #[must_use]
+    pub fn layout(&self) -> GalleryLayout {
+        self.layout
+    }
+
+    /// 图像块的标题，即上方文本区域的第一行
+    #[must_use]
+    pub fn title(&self) -> &str {
+        self.text(Section::UP).first().map_or("", String::as_str)
+    }
+
+    /// 所有图片的来源，第一张为 `pic_path`
+    pub fn sources(&self) -> impl Iterator<Item = &ImageSource> {
+        std::iter::once(&self.pic_path).chain(&self.gallery)
+    }
+
+    /// 打开并解码所有图片
+    ///
+    /// # Errors
+    ///
+    /// * `ImageError` - 如果打开或解码图片时发生错误
+    pub fn open(&self) -> Result<Vec<DynamicImage>> {
+        self.sources().map(ImageSource::open).collect()
+    }
+
+    /// 计算各文本区域的背景框和每行文本的位置
+    ///
+    /// # Parameters
+    ///
+    /// * `si` - 包含屏幕信息和文本区域的大图像实例
+    ///
+    /// # Errors
+    ///
+    /// * `TryFromIntError` - 如果在类型转换过程中发生溢出或其他错误
+    pub(crate) fn text_boxes<'a>(&'a self, si: &'a BigImg) -> Result<Vec<TextBox<'a>>> {
+        let width_chunk = si.chunk_width(self);
+        let heights = section::heights(&si.sections, si.screen.1.saturating_sub(si.pic_h));
+        let mut boxes = Vec::with_capacity(si.sections.len());
+        let mut top = si.pic_h;
+        for (index, (section, h)) in si.sections.iter().zip(heights).enumerate() {
+            if h == 0 {
+                continue;
+            }
+            let rect =
+                Rect::at(1, i32::try_from(top)?).of_size(width_chunk.saturating_sub(1).max(1), h);
+
+            let text = self.text(section.name());
+            let mut lines = Vec::with_capacity(text.len());
+            if !text.is_empty() {
+                let line_h = h.saturating_sub(section.padding_px()) / u32::try_from(text.len())?;
+                if line_h > 0 {
+                    for (i, str) in (0..).zip(text) {
+                        let high = top + i * line_h;
+                        let line_rect = Rect::at(10, i32::try_from(high)?)
+                            .of_size(width_chunk.saturating_sub(20).max(1), line_h);
+                        lines.push((line_rect, str.as_str()));
+                    }
+                }
+            }
+            boxes.push(TextBox {
+                index,
+                section,
+                rect,
+                lines,
+            });
+            top += h;
+        }
+        Ok(boxes)
+    }
+
+    /// 分隔块中分组标题的文本框
+    pub(crate) fn divider_title_rect(si: &BigImg, width_chunk: u32) -> Rect {
+        Rect::at(10, 0).of_size(width_chunk.saturating_sub(20).max(1), si.screen.1)
     }
 
     /// 绘制 Chunk 数据到一个图像上
@@ -68,90 +388,159 @@ impl Chunk {
     ///
     /// # Returns
     ///
-    /// 返回一个包含绘制数据的 `DynamicImage` 实例。
+    /// 返回一个包含绘制数据的 `DynamicImage` 实例，以及按溢出处理方式
+    /// 截断或裁剪后绘制的文本。
     ///
     /// # Errors
     ///
     /// * `ImageError` - 如果打开或处理图片时发生错误
     /// * `TryFromIntError` - 如果在类型转换过程中发生溢出或其他错误
-    pub fn draw_data(&self, si: &BigImg) -> Result<DynamicImage> {
+    pub fn draw_data(&self, si: &BigImg) -> Result<(DynamicImage, Vec<Overflowed>)> {
+        self.draw_with(si, &self.open()?)
+    }
+
+    /// 使用已解码的图片绘制 Chunk 数据
+    ///
+    /// # Parameters
+    ///
+    /// * `si` - 包含屏幕信息和样式的大图像实例
+    /// * `imgs` - 已解码的所有图片，多个输出目标可以共用
+    ///
+    /// # Returns
+    ///
+    /// 返回绘制的图像，以及在最小字号下仍放不下、按溢出处理方式绘制的文本。
+    ///
+    /// # Errors
+    ///
+    /// * `ImageError` - 如果处理图片时发生错误
+    /// * `InvalidFont` - 如果样式覆盖中的字体无效
+    /// * `TextOverflow` - 如果文本放不下且溢出处理方式为 `Error`
+    /// * `TryFromIntError` - 如果在类型转换过程中发生溢出或其他错误
+    pub fn draw_with(
+        &self,
+        si: &BigImg,
+        imgs: &[DynamicImage],
+    ) -> Result<(DynamicImage, Vec<Overflowed>)> {
         // 解构 BigImg 实例，获取所需的字段
-        let BigImg {
-            screen,
-            width_chunk,
-            text_background_color,
+        let width_chunk = si.chunk_width(self);
+        let BigImg { screen, pic_h, .. } = si;
+
+        // 将该图像块的样式覆盖合并到默认样式上
+        let style = self.style.merge(si)?;
+        let Style {
+            ref section_colors,
             text_color,
             max_scale,
-            pic_h,
-            text_up_h,
-            text_down_h,
-            font,
+            ref font,
+            radius,
+            ref effect,
             ..
-        } = si;
+        } = style;
 
         // 创建一个新的 `DynamicImage` 实例作为绘制目标
-        let mut target = DynamicImage::new_rgba8(*width_chunk, screen.1);
-
-        // 打开并调整图片大小
-        let img = image::open(&self.pic_path)
-            .map_err(|e| err_new_image!(e))?
-            .thumbnail(*width_chunk, *pic_h);
-        let (img_w, img_h) = img.dimensions();
-        // 将调整好大小的图片复制到目标图像的中心位置
-        target
-            .copy_from(&img, (width_chunk - img_w) / 2, (pic_h - img_h) / 2)
-            .map_err(|e| err_new_image!(e))?;
+        let mut target = DynamicImage::new_rgba8(width_chunk, screen.1);
+        let mut overflows = Vec::new();
 
-        // 绘制上下文本的背景框
-        let text_up_rect = Rect::at(1, i32::try_from(*pic_h)?).of_size(width_chunk - 1, *text_up_h);
-        let text_down_rect =
-            Rect::at(1, i32::try_from(pic_h + text_up_h)?).of_size(width_chunk - 1, *text_down_h);
-        target.draw_filled_rounded_rect_mut(text_up_rect, 10, text_background_color.0);
-        target.draw_filled_rounded_rect_mut(text_down_rect, 10, text_background_color.1);
-
-        // 获取上下文本的内容和长度
-        let (text_up, text_down) = (&self.text_up, &self.text_down);
-        let (len_up, len_down) = (text_up.len(), text_down.len());
-        let (h_up, h_down) = (
-            text_up_h / u32::try_from(len_up)?,
-            (text_down_h - 30) / u32::try_from(len_down)?,
-        );
-
-        // 绘制上文本
-        for (i, str) in text_up.iter().enumerate() {
-            let high = pic_h + u32::try_from(i)? * h_up;
-            target.draw_text_center_mut(
-                *text_color,
-                Rect::at(10, i32::try_from(high)?).of_size(width_chunk - 20, h_up),
-                *max_scale,
-                &font,
-                str,
+        // 分隔块只在整个高度上绘制背景框和分组标题
+        if self.is_divider() {
+            let color = section_colors.first().copied().unwrap_or(text_color);
+            target.draw_filled_rounded_rect_mut(
+                Rect::at(1, 0).of_size(width_chunk.saturating_sub(1).max(1), screen.1),
+                radius,
+                color,
             );
+            let overflow = target.draw_rich_text_mut(
+                text_color,
+                Self::divider_title_rect(si, width_chunk),
+                font,
+                &style.runs(self.title()),
+                &si.limit_text(TextStyle::new(max_scale)),
+                effect,
+            );
+            record_overflow(self.title(), overflow, &mut overflows)?;
+            return Ok((target, overflows));
         }
 
-        // 绘制下文本
-        for (i, str) in text_down.iter().enumerate() {
-            let high = pic_h + text_up_h + u32::try_from(i)? * h_down;
-            target.draw_text_center_mut(
-                *text_color,
-                Rect::at(10, i32::try_from(high)?).of_size(width_chunk - 20, h_down),
-                *max_scale,
-                &font,
-                str,
+        // 按布局调整图片大小并复制到目标图像的图片区域
+        let picture = gallery::compose(self.layout, imgs, width_chunk, *pic_h)?;
+        target
+            .copy_from(&picture, 0, 0)
+            .map_err(|e| err_new_image!(e))?;
+
+        // 按权重依次绘制各文本区域的背景框和文本
+        for TextBox {
+            index,
+            section,
+            rect,
+            lines,
+        } in self.text_boxes(si)?
+        {
+            target.draw_filled_rounded_rect_mut(rect, radius, section_colors[index]);
+            let text_style = style.spaced(
+                section
+                    .text_style(max_scale, si.min_scale)
+                    .overflow(si.overflow),
             );
-            // target.text_center(
-            //     *text_color,
-            //     Rect::at(10, i32::try_from(high)?).of_size(width_chunk - 20, h_down),
-            //     *max_scale,
-            //     &font,
-            //     str,
-            // );
+            for (rect, str) in lines {
+                let runs = style.runs(str);
+                let overflow =
+                    target.draw_rich_text_mut(text_color, rect, font, &runs, &text_style, effect);
+                record_overflow(str, overflow, &mut overflows)?;
+            }
         }
 
         // 绘制分割线
-        target.draw_line_segment_mut((0.0, 10.0), (0.0, screen.1 as f32), *text_color);
+        target.draw_line_segment_mut((0.0, 10.0), (0.0, screen.1 as f32), text_color);
+
+        // 返回绘制完成的图像和放不下的文本
+        Ok((target, overflows))
+    }
+}
+
+/// 绘制时在最小字号下仍放不下的文本及其溢出情况
+pub type Overflowed = (String, TextOverflow);
+
+/// 记录放不下的文本，溢出处理方式为 `Error` 时拒绝渲染
+fn record_overflow(
+    text: &str,
+    overflow: Option<TextOverflow>,
+    overflows: &mut Vec<Overflowed>,
+) -> Result<()> {
+    match overflow {
+        Some(o) if o.overflow == Overflow::Error => Err(err_new!(
+            Kind::TextOverflow,
+            &format!("text {text:?} overflows its box at {:.1}px", o.scale)
+        )),
+        Some(o) => {
+            overflows.push((text.to_string(), o));
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sections() {
+        let chunk: Chunk = serde_json::from_str(
+            r#"{"pic_path": "a.png", "text_up": ["a"], "text_down": ["b"],
+                "sections": {"footer": ["c"]}, "note": ["ignored"]}"#,
+        )
+        .unwrap();
+        assert_eq!(chunk.text(Section::UP), ["a"]);
+        assert_eq!(chunk.text(Section::DOWN), ["b"]);
+        assert_eq!(chunk.text("footer"), ["c"]);
+        // 未知字段不是文本区域
+        assert!(chunk.text("note").is_empty());
 
-        // 返回绘制完成的图像
-        Ok(target)
+        // 默认布局的文本仍保存在原来的字段中
+        let chunk = chunk.with_text("text_up", vec![String::from("d")]);
+        let value = serde_json::to_value(&chunk).unwrap();
+        assert_eq!(value["text_up"][0], "d");
+        assert_eq!(value["sections"]["footer"][0], "c");
+        assert!(value["sections"].get("text_up").is_none());
     }
 }
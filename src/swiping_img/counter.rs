@@ -0,0 +1,239 @@
+use super::{
+    filter::{quote, FilterGraph},
+    watermark::Corner,
+};
+use image::Rgba;
+use std::path::PathBuf;
+
+/// 进度条所在的屏幕边缘
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Edge {
+    Top,
+    #[default]
+    Bottom,
+}
+
+/// 进度条
+///
+/// # Parameters
+///
+/// * `edge`: 进度条所在的屏幕边缘。
+/// * `thickness`: 进度条的粗细（像素）。
+/// * `color`: 进度条的颜色。
+#[derive(Clone, Debug)]
+pub struct ProgressBar {
+    edge: Edge,
+    thickness: u32,
+    color: Rgba<u8>,
+}
+
+impl ProgressBar {
+    /// 创建进度条
+    ///
+    /// # Panics
+    /// - 如果 `thickness` 为零，程序将 panic
+    ///
+    #[must_use]
+    pub fn new(edge: Edge, thickness: u32, color: impl Into<Rgba<u8>>) -> Self {
+        assert_ne!(thickness, 0, "Progress bar thickness must be non-zero.");
+        Self {
+            edge,
+            thickness,
+            color: color.into(),
+        }
+    }
+}
+
+/// 序号计数器
+///
+/// 固定在屏幕上，显示当前位于屏幕中央的图像块序号（如 `37 / 300`），
+/// 序号由滚动时间线计算。
+///
+/// # Parameters
+///
+/// * `font_file`: `FFmpeg` `drawtext` 使用的字体文件路径。
+/// * `font_size`: 字号。
+/// * `color`: 文字颜色。
+/// * `corner`: 计数器所在的屏幕角落。
+/// * `offset`: 计数器距离角落的偏移（水平，垂直）。
+/// * `progress_bar`: 可选的进度条。
+#[derive(Clone, Debug)]
+pub struct Counter {
+    font_file: PathBuf,
+    font_size: u32,
+    color: Rgba<u8>,
+    corner: Corner,
+    offset: (u32, u32),
+    progress_bar: Option<ProgressBar>,
+}
+
+impl Counter {
+    /// 创建计数器
+    ///
+    /// # Parameters
+    /// - `font_file`: 字体文件路径。
+    ///
+    #[must_use]
+    pub fn new(font_file: impl Into<PathBuf>) -> Self {
+        Self {
+            font_file: font_file.into(),
+            font_size: 48,
+            color: Rgba([255, 255, 255, 255]),
+            corner: Corner::TopLeft,
+            offset: (40, 40),
+            progress_bar: None,
+        }
+    }
+
+    /// 设置字号
+    ///
+    /// # Panics
+    /// - 如果 `font_size` 为零，程序将 panic
+    ///
+    pub fn font_size(&mut self, font_size: u32) -> &mut Self {
+        assert_ne!(font_size, 0, "Counter font size must be non-zero.");
+        self.font_size = font_size;
+        self
+    }
+
+    /// 设置文字颜色
+    pub fn color(&mut self, color: impl Into<Rgba<u8>>) -> &mut Self {
+        self.color = color.into();
+        self
+    }
+
+    /// 设置计数器位置
+    ///
+    /// # Parameters
+    /// - `corner`: 计数器所在的屏幕角落。
+    /// - `offset`: 计数器距离角落的偏移（水平，垂直）。
+    ///
+    pub fn position(&mut self, corner: Corner, offset: (u32, u32)) -> &mut Self {
+        self.corner = corner;
+        self.offset = offset;
+        self
+    }
+
+    /// 设置进度条
+    pub fn progress_bar(&mut self, progress_bar: ProgressBar) -> &mut Self {
+        self.progress_bar = Some(progress_bar);
+        self
+    }
+
+    /// 按输出目标的缩放比例调整字号、偏移和进度条粗细
+    pub(crate) fn scale_by(&mut self, factor: f32) {
+        let scale = |v: u32| ((v as f32 * factor).round() as u32).max(1);
+        self.font_size = scale(self.font_size);
+        self.offset = (scale(self.offset.0), scale(self.offset.1));
+        if let Some(ref mut bar) = self.progress_bar {
+            bar.thickness = scale(bar.thickness);
+        }
+    }
+
+    /// 将计数器和进度条叠加到滤镜图上
+    ///
+    /// # Parameters
+    /// - `graph`: 当前部分视频的滤镜图。
+    /// - `x`: 屏幕左边缘在整条长图中横坐标的表达式，可以包含时间 `t`。
+    /// - `index`: 由横坐标表达式计算当前位于屏幕中央的图像块序号（从 0 开始）的表达式。
+    /// - `total`: 图像块总数。
+    /// - `max_x`: 屏幕左边缘横坐标的最大值，即滚动总距离。
+    ///
+    pub(crate) fn apply(
+        &self,
+        graph: &mut FilterGraph,
+        x: &str,
+        index: &str,
+        total: usize,
+        max_x: u32,
+    ) {
+        let (ox, oy) = self.offset;
+        let (tx, ty) = match self.corner {
+            Corner::TopLeft => (format!("{ox}"), format!("{oy}")),
+            Corner::TopRight => (format!("w-tw-{ox}"), format!("{oy}")),
+            Corner::BottomLeft => (format!("{ox}"), format!("h-th-{oy}")),
+            Corner::BottomRight => (format!("w-tw-{ox}"), format!("h-th-{oy}")),
+        };
+        let font_file = quote(&self.font_file.to_string_lossy());
+        graph.filter(&format!(
+            "drawtext=fontfile={font_file}:fontsize={}:fontcolor={}:x={tx}:y={ty}:\
+             text='%{{eif\\:{index}+1\\:d}} / {total}'",
+            self.font_size,
+            hex(self.color)
+        ));
+
+        if let Some(ref bar) = self.progress_bar {
+            let progress = if max_x == 0 {
+                String::from("1")
+            } else {
+                format!("({x})/{max_x}")
+            };
+            let y = match bar.edge {
+                Edge::Top => String::from("0"),
+                Edge::Bottom => format!("H-{}", bar.thickness),
+            };
+            let (w, _) = graph.screen();
+            graph.overlay(
+                &format!(
+                    "color=c={}:s={w}x{}:r={}",
+                    hex(bar.color),
+                    bar.thickness,
+                    graph.fps()
+                ),
+                &format!("x=W*{progress}-W:y={y}:shortest=1"),
+            );
+        }
+    }
+}
+
+/// 将颜色转换为 `FFmpeg` 颜色字符串
+fn hex(color: Rgba<u8>) -> String {
+    let Rgba([r, g, b, a]) = color;
+    format!("0x{r:02x}{g:02x}{b:02x}@{:.3}", f32::from(a) / 255.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex(Rgba([255, 0, 16, 255])), "0xff0010@1.000");
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut counter = Counter::new("C:/fonts/a.ttf");
+        counter
+            .position(Corner::BottomRight, (10, 20))
+            .progress_bar(ProgressBar::new(Edge::Top, 6, [255, 0, 0, 255]));
+        let mut graph = FilterGraph::new("white", (1920, 1080), 60, "shortest=1");
+        counter.apply(
+            &mut graph,
+            "100+t*160",
+            "2+gte(100+t*160+960,1440)",
+            300,
+            4000,
+        );
+        let (_, args) = graph.build();
+
+        // 序号从 0 开始计算，显示时加一，字体路径中的冒号需要转义
+        assert!(args[1].contains(
+            "drawtext=fontfile='C\\:/fonts/a.ttf':fontsize=48:fontcolor=0xffffff@1.000:\
+             x=w-tw-10:y=h-th-20:text='%{eif\\:2+gte(100+t*160+960,1440)+1\\:d} / 300'"
+        ));
+        // 进度条随屏幕左边缘从左侧滑入
+        assert!(args[1].contains("color=c=0xff0000@1.000:s=1920x6:r=60"));
+        assert!(args[1].contains("overlay=x=W*(100+t*160)/4000-W:y=0:shortest=1"));
+
+        // Windows 路径中的反斜杠同样需要转义
+        let mut graph = FilterGraph::new("white", (1920, 1080), 60, "shortest=1");
+        Counter::new(r"E:\fonts\a.ttf").apply(&mut graph, "0", "0", 1, 0);
+        assert!(graph.build().1[1].contains(r"drawtext=fontfile='E\:\\fonts\\a.ttf':"));
+
+        // 没有滚动距离时进度条始终是满的
+        let mut graph = FilterGraph::new("white", (1920, 1080), 60, "shortest=1");
+        counter.apply(&mut graph, "0", "0", 1, 0);
+        assert!(graph.build().1[1].contains("overlay=x=W*1-W"));
+    }
+}
@@ -0,0 +1,141 @@
+use std::path::Path;
+
+/// `FFmpeg` 滤镜图构建器
+///
+/// 以背景色和素材图片（输入 0）的叠加作为起点，
+/// 之后的每一步都在上一步的输出画面上继续叠加，
+/// 最终输出标签通过 `-map` 指定。
+pub(crate) struct FilterGraph {
+    inputs: Vec<String>,
+    chains: Vec<String>,
+    last: String,
+    labels: usize,
+    screen: (u32, u32),
+    fps: u32,
+}
+
+impl FilterGraph {
+    /// 创建滤镜图
+    ///
+    /// # Parameters
+    /// - `background`: 背景颜色。
+    /// - `screen`: 画面分辨率。
+    /// - `fps`: 帧率。
+    /// - `overlay`: 素材图片叠加到背景上的 `overlay` 参数。
+    ///
+    pub(crate) fn new(background: &str, screen: (u32, u32), fps: u32, overlay: &str) -> Self {
+        Self {
+            inputs: Vec::new(),
+            chains: vec![format!(
+                "color={background}:s={}x{}:r={fps}[bg];[bg][0]overlay={overlay}[v0]",
+                screen.0, screen.1
+            )],
+            last: String::from("v0"),
+            labels: 1,
+            screen,
+            fps,
+        }
+    }
+
+    /// 画面分辨率
+    pub(crate) fn screen(&self) -> (u32, u32) {
+        self.screen
+    }
+
+    /// 帧率
+    pub(crate) fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    fn label(&mut self) -> String {
+        let label = format!("v{}", self.labels);
+        self.labels += 1;
+        label
+    }
+
+    /// 添加一路循环图片输入，返回其输入序号
+    pub(crate) fn input(&mut self, pic_name: &Path) -> usize {
+        self.inputs.push(pic_name.to_string_lossy().into_owned());
+        self.inputs.len()
+    }
+
+    /// 在当前画面上直接应用滤镜，如 `drawtext`
+    pub(crate) fn filter(&mut self, filter: &str) {
+        let out = self.label();
+        self.chains.push(format!("[{}]{filter}[{out}]", self.last));
+        self.last = out;
+    }
+
+    /// 将 `source` 产生的画面叠加到当前画面上
+    ///
+    /// # Parameters
+    /// - `source`: 产生叠加画面的滤镜链，如 `[1]format=rgba`。
+    /// - `options`: `overlay` 滤镜参数。
+    ///
+    pub(crate) fn overlay(&mut self, source: &str, options: &str) {
+        let src = self.label();
+        let out = self.label();
+        self.chains.push(format!("{source}[{src}]"));
+        self.chains
+            .push(format!("[{}][{src}]overlay={options}[{out}]", self.last));
+        self.last = out;
+    }
+
+    /// 生成额外输入参数、`-filter_complex` 参数和 `-map` 参数
+    pub(crate) fn build(self) -> (Vec<String>, Vec<String>) {
+        let inputs = self
+            .inputs
+            .into_iter()
+            .flat_map(|i| ["-loop".to_string(), "1".to_string(), "-i".to_string(), i])
+            .collect();
+        let graph = vec![
+            "-filter_complex".to_string(),
+            self.chains.join(";"),
+            "-map".to_string(),
+            format!("[{}]", self.last),
+        ];
+        (inputs, graph)
+    }
+}
+
+/// 按 `FFmpeg` 的滤镜引用规则转义滤镜选项的值
+///
+/// 先为选项解析转义 `\`、`'` 和 `:`，再用单引号包住整个值交给滤镜图解析，
+/// 值中的 `'` 在引号外转义。
+pub(crate) fn quote(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '\'' | ':') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    format!("'{}'", escaped.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("C:/fonts/a.ttf"), r"'C\:/fonts/a.ttf'");
+        assert_eq!(quote(r"E:\fonts\a.ttf"), r"'E\:\\fonts\\a.ttf'");
+        assert_eq!(quote("it's.ttf"), r"'it\'\''s.ttf'");
+    }
+
+    #[test]
+    fn test_build() {
+        let mut graph = FilterGraph::new("white", (1920, 1080), 60, "shortest=1");
+        let index = graph.input(Path::new("watermark.png"));
+        graph.overlay(&format!("[{index}]format=rgba"), "x=10:y=10");
+        let (inputs, args) = graph.build();
+        assert_eq!(inputs, ["-loop", "1", "-i", "watermark.png"]);
+        assert_eq!(
+            args[1],
+            "color=white:s=1920x1080:r=60[bg];[bg][0]overlay=shortest=1[v0];\
+             [1]format=rgba[v1];[v0][v1]overlay=x=10:y=10[v2]"
+        );
+        assert_eq!(args[3], "[v2]");
+    }
+}
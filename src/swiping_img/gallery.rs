@@ -0,0 +1,203 @@
+use super::filter::FilterGraph;
+use crate::{err_new_image, error::Result};
+use image::{DynamicImage, GenericImage, GenericImageView};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// 图像块中多张图片的布局方式
+///
+/// * `SideBySide`: 并排排列。
+/// * `Stacked`: 上下堆叠。
+/// * `Crossfade`: 图像块可见时按 `period` 秒轮流显示，切换时淡入 `fade` 秒。
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GalleryLayout {
+    #[default]
+    SideBySide,
+    Stacked,
+    Crossfade {
+        period: f32,
+        fade: f32,
+    },
+}
+
+impl GalleryLayout {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// 将图片缩小后居中放入 `width` x `height` 的区域
+pub(crate) fn fit(img: &DynamicImage, width: u32, height: u32) -> Result<DynamicImage> {
+    let mut cell = DynamicImage::new_rgba8(width, height);
+    let img = img.thumbnail(width, height);
+    let (img_w, img_h) = img.dimensions();
+    cell.copy_from(&img, (width - img_w) / 2, (height - img_h) / 2)
+        .map_err(|e| err_new_image!(e))?;
+    Ok(cell)
+}
+
+/// 按布局将多张图片组合到 `width` x `height` 的图片区域中
+///
+/// 轮播布局只绘制第一张图片，其余图片在生成视频时叠加。
+///
+/// # Errors
+/// - 如果图像处理过程中发生错误，则返回 `Err`。
+///
+pub(crate) fn compose(
+    layout: GalleryLayout,
+    imgs: &[DynamicImage],
+    width: u32,
+    height: u32,
+) -> Result<DynamicImage> {
+    let n = u32::try_from(imgs.len())?;
+    if n <= 1 || matches!(layout, GalleryLayout::Crossfade { .. }) {
+        return match imgs.first() {
+            Some(img) => fit(img, width, height),
+            None => Ok(DynamicImage::new_rgba8(width, height)),
+        };
+    }
+
+    let mut area = DynamicImage::new_rgba8(width, height);
+    for (i, img) in (0..).zip(imgs) {
+        let (cell, x, y) = match layout {
+            GalleryLayout::Stacked => (fit(img, width, height / n)?, 0, i * (height / n)),
+            _ => (fit(img, width / n, height)?, i * (width / n), 0),
+        };
+        area.copy_from(&cell, x, y).map_err(|e| err_new_image!(e))?;
+    }
+    Ok(area)
+}
+
+/// 生成视频时叠加的轮播图片
+///
+/// # Parameters
+///
+/// * `pics`: 第二张及以后的图片区域文件，位于工作路径中。
+/// * `x`: 图片区域在素材图片中的横坐标。
+/// * `width`: 图片区域宽度。
+/// * `period`: 每张图片显示的时长（秒）。
+/// * `fade`: 切换时淡入淡出的时长（秒）。
+#[derive(Clone, Debug)]
+pub(crate) struct Crossfade {
+    pub(crate) pics: Vec<PathBuf>,
+    pub(crate) x: u32,
+    pub(crate) width: u32,
+    pub(crate) period: f32,
+    pub(crate) fade: f32,
+}
+
+impl Crossfade {
+    /// 计算图片依次显示的时间段
+    ///
+    /// # Parameters
+    /// - `x0`: 该部分视频开始时屏幕左边缘在素材图片中的横坐标。
+    /// - `speed`: 滑动速度（像素每秒），封面和结尾为 `0`。
+    /// - `elapsed`: 该部分视频开始时在整个视频中的时间（秒）。
+    /// - `duration`: 该部分视频的时长（秒）。
+    /// - `screen_w`: 屏幕宽度。
+    ///
+    /// # Results
+    /// 返回 `(图片序号, 开始时间, 结束时间)` 列表，时间相对于该部分视频开始，
+    /// 图片序号从 1 开始，序号 0 的图片已绘制在素材图片上。
+    ///
+    pub(crate) fn schedule(
+        &self,
+        x0: u32,
+        speed: f32,
+        elapsed: f32,
+        duration: f32,
+        screen_w: u32,
+    ) -> Vec<(usize, f32, f32)> {
+        let x = self.x as f32 - x0 as f32;
+        let (w, s) = (self.width as f32, screen_w as f32);
+        let (start, end) = if speed > 0.0 {
+            ((x - s) / speed, (x + w) / speed)
+        } else if x < s && x + w > 0.0 {
+            (0.0, duration)
+        } else {
+            return Vec::new();
+        };
+        let (start, end) = (start.max(0.0), end.min(duration));
+        if start >= end || self.period <= 0.0 {
+            return Vec::new();
+        }
+
+        let n = self.pics.len() + 1;
+        let first = ((elapsed + start) / self.period).floor() as usize;
+        let last = ((elapsed + end) / self.period).ceil() as usize;
+        (first..last)
+            .filter(|m| m % n != 0)
+            .filter_map(|m| {
+                let a = (m as f32 * self.period - elapsed).max(start);
+                let b = ((m + 1) as f32 * self.period - elapsed).min(end);
+                (a < b).then_some((m % n, a, b))
+            })
+            .collect()
+    }
+
+    /// 将轮播图片叠加到滤镜图上
+    ///
+    /// # Parameters
+    /// - `graph`: 当前部分视频的滤镜图。
+    /// - 其余参数同 [`Crossfade::schedule`]。
+    ///
+    pub(crate) fn apply(
+        &self,
+        graph: &mut FilterGraph,
+        x0: u32,
+        speed: f32,
+        elapsed: f32,
+        duration: f32,
+    ) {
+        let screen_w = graph.screen().0;
+        for (k, a, b) in self.schedule(x0, speed, elapsed, duration, screen_w) {
+            let fade = self.fade.min((b - a) / 2.0);
+            let index = graph.input(&self.pics[k - 1]);
+            graph.overlay(
+                &format!(
+                    "[{index}]format=rgba,fade=t=in:st={a}:d={fade}:alpha=1,\
+                     fade=t=out:st={}:d={fade}:alpha=1",
+                    b - fade
+                ),
+                &format!("x={}-{x0}-t*{speed}:y=0:shortest=1", self.x),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule() {
+        let crossfade = Crossfade {
+            pics: vec![PathBuf::from("a.png")],
+            x: 0,
+            width: 480,
+            period: 2.0,
+            fade: 0.5,
+        };
+        // 静止画面中一直可见，第二张图片在第 2~4 秒显示
+        assert_eq!(
+            crossfade.schedule(0, 0.0, 0.0, 5.0, 1920),
+            vec![(1, 2.0, 4.0)]
+        );
+        // 不可见时不叠加
+        let hidden = Crossfade {
+            x: 2400,
+            ..crossfade.clone()
+        };
+        assert!(hidden.schedule(0, 0.0, 0.0, 5.0, 1920).is_empty());
+        assert_eq!(
+            hidden.schedule(960, 0.0, 0.0, 5.0, 1920),
+            vec![(1, 2.0, 4.0)]
+        );
+        // 滑动时只在可见时间段内轮播
+        assert_eq!(
+            crossfade.schedule(0, 160.0, 1.0, 10.0, 1920),
+            vec![(1, 1.0, 3.0)]
+        );
+    }
+}
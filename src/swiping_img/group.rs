@@ -0,0 +1,170 @@
+use super::{
+    query::{field, find_date},
+    Chunk, ChunkStyle,
+};
+use crate::{
+    err_new,
+    error::{Error, Kind, Result},
+};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// 分组键的取值方式
+///
+/// * `Value`: 字段的完整文本。
+/// * `Year`: 字段中第一个日期的年份。
+/// * `Month`: 字段中第一个日期的月份，有年份时包含年份。
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    #[default]
+    Value,
+    Year,
+    Month,
+}
+
+/// 分组
+///
+/// 分组键变化时插入一个分隔块，分隔块的标题为新的分组键，
+/// 生成视频时每个分隔块对应一个章节。
+///
+/// # Parameters
+///
+/// * `key`: 分组字段，即附加字段或文本区域的名称。
+/// * `by`: 分组键的取值方式。
+/// * `width`: 分隔块的宽度倍数。
+/// * `style`: 分隔块的样式覆盖。
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Grouping {
+    key: String,
+    #[serde(default)]
+    by: GroupBy,
+    #[serde(default = "default_width")]
+    width: f32,
+    #[serde(default, skip_serializing_if = "ChunkStyle::is_empty")]
+    style: ChunkStyle,
+}
+
+fn default_width() -> f32 {
+    0.5
+}
+
+impl Grouping {
+    /// 按字段分组
+    ///
+    /// # Parameters
+    /// - `key`: 分组字段。
+    /// - `by`: 分组键的取值方式。
+    ///
+    #[must_use]
+    pub fn new(key: impl Into<String>, by: GroupBy) -> Self {
+        Self {
+            key: key.into(),
+            by,
+            width: default_width(),
+            style: ChunkStyle::default(),
+        }
+    }
+
+    /// 设置分隔块的宽度倍数
+    ///
+    /// # Panics
+    /// - 如果 `width` 不是正数，程序将 panic
+    ///
+    pub fn width(&mut self, width: f32) -> &mut Self {
+        assert!(width > 0.0, "Divider width must be positive.");
+        self.width = width;
+        self
+    }
+
+    /// 设置分隔块的样式覆盖
+    pub fn style(&mut self, style: ChunkStyle) -> &mut Self {
+        self.style = style;
+        self
+    }
+
+    /// 图像块的分组键，缺少字段或日期时为 `None`
+    fn label(&self, chunk: &Chunk) -> Option<String> {
+        let text = field(chunk, &self.key)?;
+        match self.by {
+            GroupBy::Value => Some(text),
+            GroupBy::Year => match find_date(&text)? {
+                (0, ..) => None,
+                (y, ..) => Some(format!("{y}年")),
+            },
+            GroupBy::Month => match find_date(&text)? {
+                (0, m, _) => Some(format!("{m}月")),
+                (y, m, _) => Some(format!("{y}年{m:0>2}月")),
+            },
+        }
+    }
+
+    /// 在分组键变化处插入分隔块
+    ///
+    /// 缺少分组键的图像块归入前一个分组。
+    #[must_use]
+    pub fn apply(&self, chunks: Vec<Chunk>) -> Vec<Chunk> {
+        let mut out = Vec::with_capacity(chunks.len() + 16);
+        let mut last: Option<String> = None;
+        for chunk in chunks {
+            match self.label(&chunk) {
+                Some(label) if last.as_ref() != Some(&label) => {
+                    out.push(
+                        Chunk::divider(label.clone())
+                            .with_width(self.width)
+                            .with_style(self.style.clone()),
+                    );
+                    last = Some(label);
+                }
+                _ => {}
+            }
+            out.push(chunk);
+        }
+        out
+    }
+}
+
+/// 写作 `字段` 或 `字段:year`、`字段:month`
+impl FromStr for Grouping {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (key, by) = match s.rsplit_once(':') {
+            Some((key, "year")) => (key, GroupBy::Year),
+            Some((key, "month")) => (key, GroupBy::Month),
+            Some((key, "value")) => (key, GroupBy::Value),
+            Some(_) => return Err(err_new!(Kind::Other, &format!("Invalid grouping: {s}"))),
+            None => (s, GroupBy::Value),
+        };
+        Ok(Self::new(key.trim(), by))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_apply() {
+        let chunks = ["5月1日", "5月20日", "6月3日", "生日未知", "7月7日"]
+            .into_iter()
+            .map(|d| Chunk::from_source(Path::new("a.png"), vec![], vec![d.to_string()]))
+            .collect();
+        let grouping: Grouping = "text_down:month".parse().unwrap();
+        let titles: Vec<_> = grouping
+            .apply(chunks)
+            .iter()
+            .filter(|c| c.is_divider())
+            .map(|c| (c.title().to_string(), c.width()))
+            .collect();
+        assert_eq!(
+            titles,
+            vec![
+                ("5月".to_string(), 0.5),
+                ("6月".to_string(), 0.5),
+                ("7月".to_string(), 0.5)
+            ]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use super::{BigImg, Report};
+use crate::{err_new_image, err_new_io, error::Result, prelude::debug_print};
+use std::{fmt::Write, fs, path::Path};
+
+/// 转义 HTML 文本
+fn escape(text: &str) -> String {
+    text.chars()
+        .fold(String::with_capacity(text.len()), |mut out, c| {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' => out.push_str("&quot;"),
+                '\'' => out.push_str("&#39;"),
+                _ => out.push(c),
+            }
+            out
+        })
+}
+
+impl BigImg<'_> {
+    /// 导出静态 HTML5 预览页面
+    ///
+    /// 每个图像块渲染为一张图片，与页面保存在同一目录下。
+    /// 页面以横向滚动条带展示所有图像块，CSS 动画的速度与视频滑动速度一致，
+    /// 点击画面暂停或继续，点击目录中的序号跳转到对应的图像块。
+    /// 序号与视频中的计数器一致，分隔块不编号，在目录中只显示分组标题。
+    ///
+    /// # Parameters
+    /// - `page_name`: 页面文件名，相对于工作路径。
+    ///
+    /// # Results
+    /// 返回绘制时截断或裁剪的文本的报告。
+    ///
+    /// # Errors
+    /// - 如果图像处理或保存过程中发生错误，则返回 `Err`。
+    /// - 如果页面写入失败，则返回 `Err`。
+    ///
+    pub fn export_html<P: AsRef<Path>>(&self, page_name: P) -> Result<Report> {
+        let page = self.work_dir.join(page_name);
+        let dir = page.parent().unwrap_or(&self.work_dir);
+        fs::create_dir_all(dir).map_err(|e| err_new_io!(e))?;
+
+        let mut report = Report::default();
+        let mut tiles = String::new();
+        let mut index = String::new();
+        let offsets = self.offsets(self.chunks);
+        let mut number = 0;
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            let tile_name = format!("tile_{i:0>3}.png");
+            let (tile, overflows) = chunk.draw_data(self)?;
+            report.rendered(i, chunk, self.screen, &overflows);
+            tile.save(dir.join(&tile_name))
+                .map_err(|e| err_new_image!(e))?;
+            debug_print(format!("{tile_name:?} successed"));
+
+            let title = escape(chunk.title());
+            let label = if chunk.is_divider() {
+                title.clone()
+            } else {
+                number += 1;
+                format!("{number}. {title}")
+            };
+            let _ = writeln!(
+                tiles,
+                r#"      <img src="{tile_name}" alt="{title}" title="{label}" width="{}">"#,
+                self.chunk_width(chunk)
+            );
+            let _ = writeln!(
+                index,
+                r##"    <li><a href="#" data-x="{}">{label}</a></li>"##,
+                offsets[i]
+            );
+        }
+
+        // 与视频一致：每滑过 `width_chunk` 需要 `video_swip_speed` 秒
+        let max_x = self.max_x();
+        let px_per_sec = self.width_chunk as f32 / self.video_swip_speed as f32;
+        let duration = max_x as f32 / px_per_sec;
+
+        let html = format!(
+            r##"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>Preview</title>
+  <style>
+    body {{ margin: 0; background: {background}; font-family: sans-serif; }}
+    #viewport {{ position: relative; width: {w}px; height: {h}px; overflow: hidden; cursor: pointer; }}
+    #strip {{
+      display: flex; width: max-content;
+      animation: swipe {duration}s linear {delay}s both;
+    }}
+    #strip img {{ display: block; height: {h}px; }}
+    #viewport.paused #strip {{ animation-play-state: paused; }}
+    @keyframes swipe {{ from {{ transform: translateX(0); }} to {{ transform: translateX(-{max_x}px); }} }}
+    #index {{ columns: 4; padding: 1em 2em; background: white; }}
+  </style>
+</head>
+<body>
+  <div id="viewport">
+    <div id="strip">
+{tiles}    </div>
+  </div>
+  <ol id="index">
+{index}  </ol>
+  <script>
+    const viewport = document.getElementById("viewport");
+    const strip = document.getElementById("strip");
+    viewport.addEventListener("click", () => viewport.classList.toggle("paused"));
+    document.querySelectorAll("#index a").forEach((a) => {{
+      a.addEventListener("click", (e) => {{
+        e.preventDefault();
+        const x = Math.min(a.dataset.x, {max_x});
+        strip.style.animation = "none";
+        void strip.offsetWidth;
+        strip.style.animation = "";
+        strip.style.animationDelay = -(x / {px_per_sec}) + "s";
+      }});
+    }});
+  </script>
+</body>
+</html>
+"##,
+            background = escape(&self.video_background_color),
+            w = self.screen.0,
+            h = self.screen.1,
+            delay = self.video_cover_time,
+        );
+        fs::write(&page, html).map_err(|e| err_new_io!(e))?;
+        println!("{} successed", page.to_string_lossy());
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("<a & 'b'>"), "&lt;a &amp; &#39;b&#39;&gt;");
+    }
+}
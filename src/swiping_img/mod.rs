@@ -1,17 +1,52 @@
+pub mod chapter;
 pub mod chunk;
+pub mod counter;
+mod filter;
+pub mod gallery;
+pub mod group;
+mod html;
+mod otio;
+pub mod query;
+pub mod section;
+pub mod source;
+pub mod style;
+pub mod subtitle;
+pub mod target;
+mod validate;
+pub mod watermark;
 use crate::{
-    err_new, err_new_image, err_new_tryfrom,
+    err_new, err_new_image, err_new_io,
     error::{Kind, Result},
+    imageproc::drawing::{FontChain, Outline, Overflow, Shadow, TextEffect, TextStyle},
     prelude::debug_print,
 };
 use ab_glyph::FontArc;
-pub use chunk::Chunk;
+pub use chapter::Chapter;
+pub use chunk::{Chunk, Overflowed};
+pub use counter::{Counter, Edge, ProgressBar};
+use filter::FilterGraph;
+use gallery::Crossfade;
+pub use gallery::GalleryLayout;
+pub use group::{GroupBy, Grouping};
 use image::{DynamicImage, GenericImage, GenericImageView, Rgba};
+use otio::Clip;
+pub use query::{Filter, Query};
+pub use section::Section;
+pub use source::ImageSource;
 use std::{
+    collections::HashMap,
+    ffi::OsStr,
     fmt::{self, Debug},
+    ops::{Range, RangeInclusive},
     path::{Path, PathBuf},
     process::Command,
+    sync::{Arc, Mutex},
 };
+pub use style::{ChunkStyle, TextOutline, TextShadow};
+pub use subtitle::Subtitle;
+pub use target::{EncodingProfile, OutputTarget};
+pub use validate::{Problem, Report, Severity};
+pub use watermark::{Corner, Watermark};
 
 /// 大图像处理结构体
 ///
@@ -24,20 +59,31 @@ use std::{
 /// * `chunks`: 图像块数据数组的引用。
 /// * `screen`: 显示图像的屏幕分辨率（宽度，高度）。
 /// * `step`: 每次处理图像块的数量。
-/// * `width_chunk`: 每个图像块的宽度。
-/// * `overlap`: 重叠图像块数，即屏幕能同时显示图像块的数量。
-/// * `text_background_color`: 文本的背景颜色，包括上下两种颜色。
+/// * `width_chunk`: 每个图像块的基准宽度，图像块的实际宽度为基准宽度乘以其宽度倍数。
 /// * `text_color`: 文本的颜色。
 /// * `max_scale`: 字体的最大缩放因子。
+/// * `min_scale`: 可读的最小字号，文本不会缩小到该字号以下，默认为 0，即与以往一样缩小到放下为止。
+/// * `overflow`: 文本在最小字号下仍放不下时的处理方式。
 /// * `pic_h`: 图像块中的图片区域高度。
-/// * `text_up_h`: 图像块中的上方文本的高度。
-/// * `text_down_h`: 图像块中的下方文本的高度。
-/// * `font`: 文本渲染使用的字体。
+/// * `sections`: 图片区域下方的文本区域，按权重分配剩余高度。
+/// * `font`: 文本渲染使用的字体链，每个字符使用第一个包含它的字体。
+/// * `style_fonts`: 已加载的图像块样式字体，按路径缓存，各输出目标共享。
+/// * `radius`: 文本背景框的圆角半径。
+/// * `line_height`: 行高，为字体高度的倍数。
+/// * `letter_spacing`: 字间距，以字号为单位。
+/// * `text_effect`: 文本的描边和阴影。
+/// * `markup`: 是否解析文本中的标记，默认不解析，旧数据中的 `*`、`[` 等字符原样绘制。
 /// * `video_cover_time`: 视频封面图像的持续时间。
 /// * `video_ending_time`: 视频结束图像的持续时间。
 /// * `video_background_color`: 视频的背景颜色，以字符串表示。
 /// * `video_swip_speed`: 视频的滑动速度，用视频滑动 `width_chunk` 所需的秒数表示。
 /// * `video_fps`: 视频的帧率（每秒帧数）。
+/// * `watermark`: 固定在屏幕上的水印。
+/// * `counter`: 固定在屏幕上的序号计数器和进度条。
+/// * `encoding`: 视频编码参数，由输出目标决定。
+/// * `factor`: 相对于逻辑设计的缩放比例，由输出目标决定。
+/// * `otio`: 为 `Some` 时同时导出 `OpenTimelineIO` 时间线，其中为时间线引用的音频文件。
+/// * `srt`: 是否同时导出 `SRT` 字幕。
 #[derive(Clone)]
 pub struct BigImg<'a> {
     work_dir: PathBuf,
@@ -45,44 +91,95 @@ pub struct BigImg<'a> {
     screen: (u32, u32),
     step: u32,
     width_chunk: u32,
-    overlap: u32,
-    text_background_color: (Rgba<u8>, Rgba<u8>),
     text_color: Rgba<u8>,
     max_scale: f32,
+    min_scale: f32,
+    overflow: Overflow,
     pic_h: u32,
-    text_up_h: u32,
-    text_down_h: u32,
-    font: FontArc,
+    sections: Vec<Section>,
+    font: FontChain,
+    style_fonts: Arc<Mutex<HashMap<PathBuf, FontChain>>>,
+    radius: i32,
+    line_height: f32,
+    letter_spacing: f32,
+    text_effect: TextEffect<Rgba<u8>>,
+    markup: bool,
     video_cover_time: u32,
     video_ending_time: u32,
     video_background_color: String,
     video_swip_speed: u32,
     video_fps: u32,
+    watermark: Option<Watermark>,
+    counter: Option<Counter>,
+    encoding: EncodingProfile,
+    factor: f32,
+    otio: Option<Vec<PathBuf>>,
+    srt: bool,
+}
+
+/// 分段
+///
+/// 相邻分段之间有重叠，保证上一段最后一帧与下一段第一帧相同。
+///
+/// * `range`: 分段中图像块的序号范围。
+/// * `next`: 下一段第一个图像块的序号，最后一段为 `None`。
+struct Segment {
+    range: Range<usize>,
+    next: Option<usize>,
+}
+
+/// 视频的组成部分
+#[derive(Clone, Copy)]
+enum Part {
+    /// 封面
+    Cover,
+    /// 滚动部分，`start` 为素材图片中第一个图像块的序号，`scroll` 为滚动距离（像素），
+    /// `elapsed` 和 `seconds` 为开始时间和时长（秒）
+    Scroll {
+        start: usize,
+        scroll: u32,
+        elapsed: f32,
+        seconds: f32,
+    },
+    /// 结尾，`offset` 为屏幕左边缘在素材图片中的横坐标
+    Ending { elapsed: f32, offset: u32 },
 }
 
 impl<'a> BigImg<'a> {
     #[must_use]
     pub fn new_with_default(work_dir: &Path, chunks: &'a [Chunk]) -> Self {
-        let font = FontArc::try_from_slice(include_bytes!("../MiSans-Demibold.ttf")).unwrap();
+        let font =
+            FontChain::from_data(include_bytes!("../MiSans-Demibold.ttf").as_slice()).unwrap();
         Self {
             work_dir: work_dir.to_path_buf(),
             chunks,
             screen: (1920, 1080),
             step: 40,
             width_chunk: 480,
-            text_background_color: (Rgba([23, 150, 235, 255]), Rgba([44, 85, 153, 255])),
             text_color: Rgba([255, 255, 255, 255]),
             max_scale: 120.0,
+            min_scale: 0.0,
+            overflow: Overflow::default(),
             pic_h: 520,
-            text_up_h: 214,
+            sections: Section::preset(),
             font,
+            style_fonts: Arc::default(),
+            radius: 10,
+            line_height: 1.0,
+            letter_spacing: 0.0,
+            text_effect: TextEffect::default(),
+            markup: false,
             video_cover_time: 3,
             video_ending_time: 3,
             video_background_color: String::from("white"),
             video_swip_speed: 3,
             video_fps: 60,
-            overlap: 4,
-            text_down_h: 346,
+            watermark: None,
+            counter: None,
+            encoding: EncodingProfile::default(),
+            factor: 1.0,
+            otio: None,
+            srt: false,
         }
     }
 
@@ -141,17 +238,50 @@ impl<'a> BigImg<'a> {
         self
     }
 
-    /// 设置文本背景颜色
+    /// 设置默认布局中上下两个文本区域的背景颜色
     ///
     /// # Parameters
-    /// - `color`: 文本背景颜色，使用 `(Rgba<u8>, Rgba<u8>)` 类型表示
+    /// - `color_up`: 上方文本区域的背景颜色
+    /// - `color_down`: 下方文本区域的背景颜色
     ///
     pub fn text_background_color(
         &mut self,
         color_up: impl Into<Rgba<u8>>,
         color_down: impl Into<Rgba<u8>>,
     ) -> &mut Self {
-        self.text_background_color = (color_up.into(), color_down.into());
+        let (color_up, color_down) = (color_up.into(), color_down.into());
+        for section in &mut self.sections {
+            let color = match section.name() {
+                Section::UP => color_up,
+                Section::DOWN => color_down,
+                _ => continue,
+            };
+            section.background_color(color);
+        }
+        self
+    }
+
+    /// 设置默认布局中上方文本区域的高度，下方文本区域占用其余高度
+    ///
+    /// 按当前的屏幕高度和图片区域高度换算为上下两个区域的权重，
+    /// 之后再修改屏幕或图片区域高度时两个区域按比例缩放。
+    ///
+    /// # Parameters
+    /// - `text_up_h`: 上部文本高度，必须是非零值
+    ///
+    /// # Panics
+    /// - 如果 `text_up_h` 为零，程序将 panic
+    ///
+    pub fn text_up_h(&mut self, text_up_h: u32) -> &mut Self {
+        assert_ne!(text_up_h, 0, "Upper text height must be non-zero.");
+        let rest = self.screen.1.saturating_sub(self.pic_h + text_up_h).max(1);
+        for section in &mut self.sections {
+            match section.name() {
+                Section::UP => section.weight(text_up_h as f32),
+                Section::DOWN => section.weight(rest as f32),
+                _ => continue,
+            };
+        }
         self
     }
 
@@ -165,6 +295,162 @@ impl<'a> BigImg<'a> {
         self
     }
 
+    /// 设置可读的最小字号
+    ///
+    /// 默认为 0，文本一直缩小到放下为止，与未设置最小字号时的输出相同。
+    /// 设置后，在该字号下仍放不下的文本才按 [`BigImg::overflow`] 处理。
+    ///
+    /// # Parameters
+    /// - `min_scale`: 最小字号，使用 `f32` 类型表示
+    ///
+    pub fn min_scale(&mut self, min_scale: f32) -> &mut Self {
+        self.min_scale = min_scale;
+        self
+    }
+
+    /// 设置文本在最小字号下仍放不下时的处理方式，默认以省略号截断
+    ///
+    /// 每处溢出都会记录在 [`BigImg::validate`] 的报告中。
+    ///
+    /// # Parameters
+    /// - `overflow`: `Ellipsis` 截断并加省略号，`Clip` 裁剪到文本框，`Error` 拒绝渲染
+    ///
+    pub fn overflow(&mut self, overflow: Overflow) -> &mut Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// 为文本样式加上最小字号和溢出处理方式
+    pub(crate) fn limit_text(&self, style: TextStyle) -> TextStyle {
+        style.min_scale(self.min_scale).overflow(self.overflow)
+    }
+
+    /// 设置文本渲染使用的字体链
+    ///
+    /// # Parameters
+    /// - `font`: 单个字体或按顺序回退的字体链
+    ///
+    pub fn font(&mut self, font: impl Into<FontChain>) -> &mut Self {
+        self.font = font.into();
+        self
+    }
+
+    /// 在字体链末尾追加回退字体，用于绘制前面的字体中缺少的字符
+    ///
+    /// # Parameters
+    /// - `font`: 回退字体，如符号或表情字体
+    ///
+    pub fn fallback_font(&mut self, font: FontArc) -> &mut Self {
+        self.font = self.font.clone().with_fallback(font);
+        self
+    }
+
+    /// 从字体文件数据追加回退字体，与主字体一样进行文本整形
+    ///
+    /// # Parameters
+    /// - `data`: 回退字体的文件内容
+    ///
+    /// # Errors
+    /// - 如果 `data` 不是有效的字体，则返回 `Err`。
+    ///
+    pub fn fallback_font_data(&mut self, data: impl Into<Vec<u8>>) -> Result<&mut Self> {
+        self.font = self
+            .font
+            .clone()
+            .with_fallback_data(data)
+            .map_err(|e| err_new!(Kind::InvalidFont, &e.to_string()))?;
+        Ok(self)
+    }
+
+    /// 设置行高，为字体高度的倍数
+    ///
+    /// # Panics
+    /// - 如果 `line_height` 不是正数，程序将 panic
+    ///
+    pub fn line_height(&mut self, line_height: f32) -> &mut Self {
+        assert!(line_height > 0.0, "Line height must be positive.");
+        self.line_height = line_height;
+        self
+    }
+
+    /// 设置字间距，以字号为单位，负数时收紧文本
+    pub fn letter_spacing(&mut self, letter_spacing: f32) -> &mut Self {
+        self.letter_spacing = letter_spacing;
+        self
+    }
+
+    /// 设置文本描边，使文本在图片或浅色背景上清晰可读
+    ///
+    /// # Parameters
+    /// - `width`: 描边宽度（像素）
+    /// - `color`: 描边颜色
+    ///
+    /// # Panics
+    /// - 如果 `width` 不是正数，程序将 panic
+    ///
+    pub fn text_outline(&mut self, width: f32, color: impl Into<Rgba<u8>>) -> &mut Self {
+        assert!(width > 0.0, "Outline width must be positive.");
+        self.text_effect.outline = Some(Outline {
+            width,
+            color: color.into(),
+        });
+        self
+    }
+
+    /// 设置文本阴影
+    ///
+    /// # Parameters
+    /// - `offset`: 阴影相对文本的偏移（水平，垂直）
+    /// - `blur`: 模糊半径（像素）
+    /// - `opacity`: 不透明度，范围 `0.0..=1.0`
+    /// - `color`: 阴影颜色
+    ///
+    /// # Panics
+    /// - 如果 `blur` 为负数或 `opacity` 不在 `0.0..=1.0` 范围内，程序将 panic
+    ///
+    pub fn text_shadow(
+        &mut self,
+        offset: (i32, i32),
+        blur: f32,
+        opacity: f32,
+        color: impl Into<Rgba<u8>>,
+    ) -> &mut Self {
+        assert!(blur >= 0.0, "Shadow blur must be non-negative.");
+        assert!(
+            (0.0..=1.0).contains(&opacity),
+            "Shadow opacity must be within 0.0..=1.0."
+        );
+        self.text_effect.shadow = Some(Shadow {
+            offset,
+            blur,
+            opacity,
+            color: color.into(),
+        });
+        self
+    }
+
+    /// 设置是否解析文本中的标记，如 `**粗体**` 和 `[color=#f00]红色[/color]`
+    ///
+    /// 默认不解析，已有数据中的 `*`、`[` 等字符原样绘制；图像块可以在样式中单独开启或关闭。
+    ///
+    /// # Parameters
+    /// - `markup`: 是否解析标记
+    ///
+    pub fn markup(&mut self, markup: bool) -> &mut Self {
+        self.markup = markup;
+        self
+    }
+
+    /// 设置文本背景框的圆角半径
+    ///
+    /// # Parameters
+    /// - `radius`: 圆角半径，使用 `i32` 类型表示
+    ///
+    pub fn radius(&mut self, radius: i32) -> &mut Self {
+        self.radius = radius;
+        self
+    }
+
     /// 设置图片高度
     ///
     /// # Parameters
@@ -179,17 +465,17 @@ impl<'a> BigImg<'a> {
         self
     }
 
-    /// 设置上部文本高度
+    /// 设置文本区域
     ///
     /// # Parameters
-    /// - `text_up_h`: 上部文本高度，必须是非零值
+    /// - `sections`: 自上而下排列的文本区域，默认为 [`Section::preset`]
     ///
     /// # Panics
-    /// - 如果 `text_up_h` 为零，程序将 panic
+    /// - 如果 `sections` 为空，程序将 panic
     ///
-    pub fn text_up_h(&mut self, text_up_h: u32) -> &mut Self {
-        assert_ne!(text_up_h, 0, "Upper text height must be non-zero.");
-        self.text_up_h = text_up_h;
+    pub fn sections(&mut self, sections: Vec<Section>) -> &mut Self {
+        assert!(!sections.is_empty(), "Sections must be non-empty.");
+        self.sections = sections;
         self
     }
 
@@ -242,95 +528,438 @@ impl<'a> BigImg<'a> {
         self.video_fps = video_fps;
         self
     }
+
+    /// 设置水印
+    ///
+    /// # Parameters
+    /// - `watermark`: 水印，会叠加在封面、滚动和结尾部分的每一帧上
+    ///
+    pub fn watermark(&mut self, watermark: Watermark) -> &mut Self {
+        self.watermark = Some(watermark);
+        self
+    }
+
+    /// 设置序号计数器
+    ///
+    /// # Parameters
+    /// - `counter`: 计数器，显示当前位于屏幕中央的图像块序号，可附带进度条
+    ///
+    pub fn counter(&mut self, counter: Counter) -> &mut Self {
+        self.counter = Some(counter);
+        self
+    }
+
+    /// 设置同时导出 `OpenTimelineIO` 时间线
+    ///
+    /// 导出时会保留封面、各分段和结尾的视频片段，时间线与最终视频同名，扩展名为 `.otio`。
+    ///
+    /// # Parameters
+    /// - `audio`: 时间线中引用的音频文件，每个文件占一条音频轨道
+    ///
+    pub fn otio(&mut self, audio: Vec<PathBuf>) -> &mut Self {
+        self.otio = Some(audio);
+        self
+    }
+
+    /// 设置是否同时导出 `SRT` 字幕
+    ///
+    /// 字幕与最终视频同名，扩展名为 `.srt`，内容见 [`BigImg::subtitles`]。
+    ///
+    /// # Parameters
+    /// - `srt`: 是否导出字幕
+    ///
+    pub fn srt(&mut self, srt: bool) -> &mut Self {
+        self.srt = srt;
+        self
+    }
+
+    /// 按输出目标等比缩放逻辑设计，得到该目标使用的布局。
+    ///
+    /// 缩放比例由输出高度与逻辑设计高度之比决定，
+    /// 每个目标使用工作路径下独立的子目录保存中间文件。
+    ///
+    /// # Parameters
+    /// - `index`: 输出目标序号。
+    /// - `target`: 输出目标。
+    ///
+    fn for_target(&self, index: usize, target: &OutputTarget) -> Self {
+        let factor = target.screen().1 as f32 / self.screen.1 as f32;
+        let scale = |v: u32| ((v as f32 * factor).round() as u32).max(1);
+
+        let mut si = self.clone();
+        si.work_dir = self.work_dir.join(format!("target_{index}"));
+        si.screen = target.screen();
+        si.video_fps = target.fps();
+        si.encoding = target.encoding().clone();
+        si.width_chunk = scale(self.width_chunk);
+        si.pic_h = scale(self.pic_h);
+        for section in &mut si.sections {
+            section.scale_by(factor);
+        }
+        si.max_scale = self.max_scale * factor;
+        si.min_scale = self.min_scale * factor;
+        si.radius = (self.radius as f32 * factor).round() as i32;
+        si.text_effect = self.text_effect.scaled(factor);
+        si.factor = self.factor * factor;
+        if let Some(ref mut watermark) = si.watermark {
+            watermark.scale_by(factor);
+        }
+        if let Some(ref mut counter) = si.counter {
+            counter.scale_by(factor);
+        }
+        si
+    }
 }
 
 impl BigImg<'_> {
-    /// 组合所有图像块并生成最终视频。
+    /// 组合所有图像块并生成最终视频，一次运行可以输出多个目标。
+    ///
+    /// 每张源图片只解码一次，由重叠的分段和所有输出目标共用。
     ///
     /// # Parameters
-    /// - `save_name`: 最终视频文件名。
+    /// - `targets`: 输出目标列表。
+    ///
+    /// # Results
+    /// 返回 [`BigImg::validate`] 的报告，其中只有不影响渲染的警告，由调用者决定如何展示。
+    /// 各输出目标渲染时截断或裁剪的文本也作为警告记录在报告中。
     ///
     /// # Errors
+    /// - 如果 `targets` 或图像块列表为空，如查询没有匹配的图像块，则返回 `Err`。
+    /// - 如果 [`BigImg::validate`] 发现无法渲染的问题，则返回包含完整报告的 `Err`。
     /// - 如果图像处理或保存过程中发生错误，则返回 `Err`。
     /// - 如果 `FFmpeg` 命令执行失败，则返回 `Err`。
     ///
-    pub fn run<P: AsRef<Path>>(&self, save_name: P) -> Result<()> {
-        let chunks = self.divide();
-        let mut results = Vec::with_capacity(chunks.len() + 2);
-
-        for (index, &chunk) in chunks.iter().enumerate() {
-            let target = self.combain_chunk(chunk)?;
-            if index == 0 {
-                let cover = target.crop_imm(0, 0, self.screen.0, self.screen.1);
-                let cover_pic_name = Path::new("cover.png");
-                // 保存组合后的图像
-                cover
-                    .save(self.work_dir.join(cover_pic_name))
-                    .map_err(|e| err_new_image!(e))?;
-                debug_print(format!("{cover_pic_name:?} successed"));
-
-                let cover_video_name = cover_pic_name.with_extension("mp4");
-                self.generate_endpoint_video(
-                    cover_pic_name,
-                    &cover_video_name,
-                    self.video_cover_time,
-                )?;
-                results.push(cover_video_name);
+    pub fn run(&self, targets: &[OutputTarget]) -> Result<Report> {
+        if targets.is_empty() {
+            return Err(err_new!(Kind::Other, "Empty output targets"));
+        }
+        if self.chunks.is_empty() {
+            return Err(err_new!(Kind::Other, "Empty chunks"));
+        }
+
+        let mut report = self.validate();
+        if report.has_errors() {
+            return Err(err_new!(Kind::InvalidChunk, &report.to_string()));
+        }
+
+        let layouts = targets
+            .iter()
+            .enumerate()
+            .map(|(i, target)| self.for_target(i, target))
+            .collect::<Vec<_>>();
+        for si in &layouts {
+            std::fs::create_dir_all(&si.work_dir).map_err(|e| err_new_io!(e))?;
+            if let Some(ref watermark) = si.watermark {
+                watermark.prepare(&si.work_dir, &si.font)?;
             }
+        }
 
+        // 按所有目标中最大的尺寸缩小源图片
+        let (max_w, max_h) = layouts.iter().fold((0, 0), |(w, h), si| {
+            (w.max(si.width_chunk), h.max(si.pic_h))
+        });
+
+        let segments = self.divide();
+        let mut results = vec![Vec::with_capacity(segments.len() + 2); layouts.len()];
+
+        // 相邻分段有重叠的图像块，已解码的图片留给下一段使用，每张源图片只解码一次
+        let mut decoded: Vec<Vec<DynamicImage>> = Vec::new();
+        let mut first = 0;
+        for (index, segment) in segments.iter().enumerate() {
+            decoded.drain(..segment.range.start - first);
+            first = segment.range.start;
+            for item in &self.chunks[first + decoded.len()..segment.range.end] {
+                let w = ((max_w as f32 * item.width()).ceil() as u32).max(1);
+                decoded.push(
+                    item.open()?
+                        .iter()
+                        .map(|img| img.thumbnail(w, max_h))
+                        .collect(),
+                );
+            }
+            let images = &decoded[..segment.range.len()];
+            for (si, results) in layouts.iter().zip(&mut results) {
+                si.render_segment(index, segments.len(), segment, images, results, &mut report)?;
+            }
+        }
+
+        for ((si, target), results) in layouts.iter().zip(targets).zip(&results) {
+            let save_name = self.work_dir.join(target.save_name());
+            si.combain(results, &save_name)?;
+            if let Some(ref audio) = si.otio {
+                let name = save_name.file_stem().unwrap_or_default().to_string_lossy();
+                let timeline = otio::timeline(&name, si.video_fps, &si.work_dir, results, audio);
+                otio::write(&save_name.with_extension("otio"), &timeline)?;
+            }
+            if si.srt {
+                let srt = subtitle::srt(&si.subtitles());
+                std::fs::write(save_name.with_extension("srt"), srt).map_err(|e| err_new_io!(e))?;
+            }
+            if si.watermark.is_some() {
+                let _ = std::fs::remove_file(si.work_dir.join(Watermark::PIC_NAME));
+            }
+            let _ = std::fs::remove_dir(&si.work_dir);
+        }
+        Ok(report)
+    }
+
+    /// 生成一个分段的素材图片和视频，第一段和最后一段还会生成封面和结尾。
+    ///
+    /// # Parameters
+    /// - `index`: 分段序号。
+    /// - `len`: 分段总数。
+    /// - `segment`: 分段。
+    /// - `images`: 分段中图像块对应的已解码图片。
+    /// - `results`: 生成的视频片段列表。
+    /// - `report`: 记录绘制时放不下的文本。
+    ///
+    /// # Errors
+    /// - 如果图像处理或保存过程中发生错误，则返回 `Err`。
+    /// - 如果 `FFmpeg` 命令执行失败，则返回 `Err`。
+    ///
+    fn render_segment(
+        &self,
+        index: usize,
+        len: usize,
+        segment: &Segment,
+        images: &[Vec<DynamicImage>],
+        results: &mut Vec<Clip>,
+        report: &mut Report,
+    ) -> Result<()> {
+        let start = segment.range.start;
+        let chunk = &self.chunks[segment.range.clone()];
+        let target = self.combain_chunk(start, chunk, images, report)?;
+        let crossfades = self.prepare_crossfades(start, chunk, images)?;
+        if index == 0 {
+            let cover = target.crop_imm(0, 0, self.screen.0, self.screen.1);
+            let cover_pic_name = Path::new("cover.png");
             // 保存组合后的图像
-            let mid_pic_name = format!("{index:0>2}.png");
-            let mid_pic_name = Path::new(&mid_pic_name);
-            target
-                .save(self.work_dir.join(mid_pic_name))
+            cover
+                .save(self.work_dir.join(cover_pic_name))
+                .map_err(|e| err_new_image!(e))?;
+            debug_print(format!("{cover_pic_name:?} successed"));
+
+            let cover_video_name = cover_pic_name.with_extension("mp4");
+            self.generate_endpoint_video(
+                cover_pic_name,
+                &cover_video_name,
+                self.video_cover_time,
+                Part::Cover,
+                &crossfades,
+            )?;
+            results.push(Clip {
+                name: cover_video_name,
+                frames: u64::from(self.video_cover_time) * u64::from(self.video_fps),
+            });
+        }
+
+        // 保存组合后的图像
+        let mid_pic_name = format!("{index:0>2}.png");
+        let mid_pic_name = Path::new(&mid_pic_name);
+        target
+            .save(self.work_dir.join(mid_pic_name))
+            .map_err(|e| err_new_image!(e))?;
+        debug_print(format!("{mid_pic_name:?} successed"));
+
+        let mid_video_name = mid_pic_name.with_extension("mp4");
+        let scroll = self.scroll(segment, &self.offsets(self.chunks));
+        let elapsed = self.elapsed(results);
+        let frames = self.generate_mid_video(
+            scroll,
+            start,
+            elapsed,
+            mid_pic_name,
+            &mid_video_name,
+            &crossfades,
+        )?;
+        results.push(Clip {
+            name: mid_video_name,
+            frames,
+        });
+
+        if index == len - 1 {
+            // 长图比屏幕窄时从头截取，不足部分由背景色填充
+            let offset = target.dimensions().0.saturating_sub(self.screen.0);
+            let ending = target.crop_imm(offset, 0, self.screen.0, self.screen.1);
+            let ending_pic_name = Path::new("ending.png");
+            // 保存组合后的图像
+            ending
+                .save(self.work_dir.join(ending_pic_name))
                 .map_err(|e| err_new_image!(e))?;
-            debug_print(format!("{mid_pic_name:?} successed"));
-
-            let mid_video_name = mid_pic_name.with_extension("mp4");
-            self.generate_mid_video(chunk.len() as u32, mid_pic_name, &mid_video_name)?;
-            results.push(mid_video_name);
-
-            if index == chunks.len() - 1 {
-                let w = target.dimensions().0;
-                let ending = target.crop_imm(w - self.screen.0, 0, self.screen.0, self.screen.1);
-                let ending_pic_name = Path::new("ending.png");
-                // 保存组合后的图像
-                ending
-                    .save(self.work_dir.join(ending_pic_name))
+            debug_print(format!("{ending_pic_name:?} successed"));
+
+            let ending_video_name = ending_pic_name.with_extension("mp4");
+            let elapsed = self.elapsed(results);
+            self.generate_endpoint_video(
+                ending_pic_name,
+                &ending_video_name,
+                self.video_ending_time,
+                Part::Ending { elapsed, offset },
+                &crossfades,
+            )?;
+            results.push(Clip {
+                name: ending_video_name,
+                frames: u64::from(self.video_ending_time) * u64::from(self.video_fps),
+            });
+        }
+
+        for crossfade in &crossfades {
+            for pic in &crossfade.pics {
+                let _ = std::fs::remove_file(self.work_dir.join(pic));
+            }
+        }
+        Ok(())
+    }
+
+    /// 保存分段中轮播布局图像块的其余图片，供生成视频时叠加。
+    ///
+    /// # Parameters
+    /// - `start`: 分段中第一个图像块的序号。
+    /// - `chunk`: 分段中的图像块。
+    /// - `images`: 分段中图像块对应的已解码图片。
+    ///
+    /// # Errors
+    /// - 如果图像处理或保存过程中发生错误，则返回 `Err`。
+    ///
+    fn prepare_crossfades(
+        &self,
+        start: usize,
+        chunk: &[Chunk],
+        images: &[Vec<DynamicImage>],
+    ) -> Result<Vec<Crossfade>> {
+        let offsets = self.offsets(chunk);
+        let mut crossfades = Vec::new();
+        for (i, (item, imgs)) in chunk.iter().zip(images).enumerate() {
+            let GalleryLayout::Crossfade { period, fade } = item.layout() else {
+                continue;
+            };
+            let mut pics = Vec::with_capacity(imgs.len().saturating_sub(1));
+            for (k, img) in imgs.iter().enumerate().skip(1) {
+                let pic = PathBuf::from(format!("gallery_{}_{k}.png", start + i));
+                gallery::fit(img, self.chunk_width(item), self.pic_h)?
+                    .save(self.work_dir.join(&pic))
                     .map_err(|e| err_new_image!(e))?;
-                debug_print(format!("{ending_pic_name:?} successed"));
-
-                let ending_video_name = ending_pic_name.with_extension("mp4");
-                self.generate_endpoint_video(
-                    ending_pic_name,
-                    &ending_video_name,
-                    self.video_ending_time,
-                )?;
-                results.push(ending_video_name);
+                pics.push(pic);
+            }
+            if !pics.is_empty() {
+                crossfades.push(Crossfade {
+                    pics,
+                    x: offsets[i],
+                    width: self.chunk_width(item),
+                    period,
+                    fade,
+                });
             }
         }
+        Ok(crossfades)
+    }
 
-        self.combain(&mut results, save_name.as_ref())?;
-        Ok(())
+    /// 图像块的实际宽度
+    fn chunk_width(&self, chunk: &Chunk) -> u32 {
+        ((self.width_chunk as f32 * chunk.width()).round() as u32).max(1)
     }
 
-    /// 将图像块分割成多个子块。
+    /// 图像块在长图中的起始横坐标，最后一项为长图总宽度。
+    fn offsets(&self, chunks: &[Chunk]) -> Vec<u32> {
+        let mut offsets = Vec::with_capacity(chunks.len() + 1);
+        offsets.push(0);
+        for chunk in chunks {
+            offsets.push(offsets[offsets.len() - 1] + self.chunk_width(chunk));
+        }
+        offsets
+    }
+
+    /// 分段的滚动距离（像素）
+    ///
+    /// 滚动到下一段第一个图像块位于屏幕左边缘，最后一段滚动到长图末尾。
+    fn scroll(&self, segment: &Segment, offsets: &[u32]) -> u32 {
+        let start = offsets[segment.range.start];
+        match segment.next {
+            Some(next) => offsets[next] - start,
+            None => (offsets[segment.range.end] - start).saturating_sub(self.screen.0),
+        }
+    }
+
+    /// 滚动速度（像素/秒），每 `video_swip_speed` 秒滑过 `width_chunk`
+    fn speed(&self) -> f32 {
+        self.width_chunk as f32 / self.video_swip_speed as f32
+    }
+
+    /// 滚动速度的 `FFmpeg` 表达式，用分数表示以免整除截断
+    fn speed_expr(&self) -> String {
+        format!("{}/{}", self.width_chunk, self.video_swip_speed)
+    }
+
+    /// 滚动视频的帧数，每滑过 `width_chunk` 需要 `video_swip_speed` 秒
+    ///
+    /// 滚动时间按帧向上取整，不足一秒的部分不会补成整秒；
+    /// 最后一帧尚未滑到 `scroll`，下一段的第一帧正好从那里开始。
+    fn scroll_frames(&self, scroll: u32) -> u64 {
+        (u64::from(scroll) * u64::from(self.video_swip_speed) * u64::from(self.video_fps))
+            .div_ceil(u64::from(self.width_chunk))
+            .max(1)
+    }
+
+    /// 帧数对应的时长（秒）
+    fn frames_to_seconds(&self, frames: u64) -> f32 {
+        frames as f32 / self.video_fps as f32
+    }
+
+    /// 已生成片段的总时长（秒），即下一个片段在整个视频中的开始时间
+    fn elapsed(&self, results: &[Clip]) -> f32 {
+        self.frames_to_seconds(results.iter().map(|c| c.frames).sum())
+    }
+
+    /// 将图像块分割成多个分段。
+    ///
+    /// 每段最多包含 `step` 个图像块，且宽度不小于屏幕宽度。
+    /// 下一段从本段末尾能铺满一屏的最后一个图像块开始，
+    /// 即本段滚动结束时位于屏幕左边缘的图像块。
     ///
     /// # Results
-    /// 返回一个包含分割后子块的向量。
+    /// 返回分段列表。
     ///
-    fn divide(&self) -> Vec<&[Chunk]> {
+    fn divide(&self) -> Vec<Segment> {
         let len = self.chunks.len();
-        (0..len - self.overlap as usize)
-            .step_by((self.step - self.overlap) as usize)
-            .map(|i| &self.chunks[i..(i + self.step as usize).min(len)])
-            .collect()
+        let offsets = self.offsets(self.chunks);
+        let mut segments = Vec::new();
+        let mut start = 0;
+        while start < len {
+            let mut end = (start + self.step as usize).min(len);
+            while end < len && offsets[end] - offsets[start + 1] < self.screen.0 {
+                end += 1;
+            }
+            // 本段末尾铺满一屏的图像块中最靠前的一个
+            let next = (start + 1..end)
+                .rev()
+                .find(|&k| offsets[end] - offsets[k] >= self.screen.0);
+            match next {
+                Some(next) if end < len => {
+                    segments.push(Segment {
+                        range: start..end,
+                        next: Some(next),
+                    });
+                    start = next;
+                }
+                _ => {
+                    segments.push(Segment {
+                        range: start..end,
+                        next: None,
+                    });
+                    break;
+                }
+            }
+        }
+        segments
     }
 
     /// 将多个图像块组合成一个完整的图像。
     ///
     /// # Parameters
+    /// - `start`: 第一个图像块的序号。
     /// - `chunk`: 要组合的图像块切片。
+    /// - `images`: 图像块对应的已解码图片。
+    /// - `report`: 记录绘制时放不下的文本。
     ///
     /// # Results
     /// 如果成功，则返回组合后的 `DynamicImage`；如果失败，则返回 `Err`。
@@ -339,19 +968,26 @@ impl BigImg<'_> {
     /// - 如果 `chunk` 为空，则返回 `Err`。
     /// - 如果图像处理过程中发生错误，则返回 `Err`。
     ///
-    fn combain_chunk(&self, chunk: &[Chunk]) -> Result<DynamicImage> {
+    fn combain_chunk(
+        &self,
+        start: usize,
+        chunk: &[Chunk],
+        images: &[Vec<DynamicImage>],
+        report: &mut Report,
+    ) -> Result<DynamicImage> {
         if chunk.is_empty() {
             return Err(err_new!(Kind::Other, "Empty chunk"));
         }
 
-        let len = u32::try_from(chunk.len()).map_err(|e| err_new_tryfrom!(e))?;
-        let mut target = DynamicImage::new_rgba8(len * self.width_chunk, self.screen.1);
+        let offsets = self.offsets(chunk);
+        let mut target = DynamicImage::new_rgba8(offsets[chunk.len()], self.screen.1);
 
         // 将每张图片绘制到目标图像中
-        for (i, item) in chunk.iter().enumerate() {
-            let img = item.draw_data(self).map_err(|e| err_new_image!(e))?;
+        for (i, ((item, imgs), x)) in chunk.iter().zip(images).zip(offsets).enumerate() {
+            let (img, overflows) = item.draw_with(self, imgs).map_err(|e| err_new_image!(e))?;
+            report.rendered(start + i, item, self.screen, &overflows);
             target
-                .copy_from(&img, u32::try_from(i)? * self.width_chunk, 0)
+                .copy_from(&img, x, 0)
                 .map_err(|e| err_new_image!(e))?;
         }
         Ok(target)
@@ -363,6 +999,8 @@ impl BigImg<'_> {
     /// - `pic_name`: 素材图片名称。
     /// - `video_name`: 生成视频名称。
     /// - `video_time`: 视频时长（秒）。
+    /// - `part`: 封面或结尾。
+    /// - `crossfades`: 需要叠加的轮播图片。
     ///
     /// # Errors
     /// - 如果 `FFmpeg` 命令执行失败，则返回 `Err`。
@@ -372,26 +1010,18 @@ impl BigImg<'_> {
         pic_name: &Path,
         video_name: &Path,
         video_time: u32,
+        part: Part,
+        crossfades: &[Crossfade],
     ) -> Result<()> {
-        self.ffmpeg(&[
-            "-r",
-            "1",
-            "-loop",
-            "1",
-            "-i",
-            pic_name.to_str().unwrap(),
-            "-filter_complex",
-            &format!(
-                "color={}:s={}x{}:r={}[bg];[bg][0]overlay=shortest=1",
-                self.video_background_color, self.screen.0, self.screen.1, self.video_fps
-            ),
-            "-preset",
-            "fast",
-            "-t",
-            &video_time.to_string(),
-            "-y",
-            video_name.to_str().unwrap(),
-        ])?;
+        let (inputs, graph) = self.filter_graph("shortest=1", part, crossfades);
+        let mut args = vec!["-r", "1", "-loop", "1", "-i", pic_name.to_str().unwrap()];
+        args.extend(inputs.iter().map(String::as_str));
+        args.extend(graph.iter().map(String::as_str));
+        let encoding = self.encoding.args();
+        args.extend(encoding.iter().map(String::as_str));
+        let video_time = video_time.to_string();
+        args.extend(["-t", &video_time, "-y", video_name.to_str().unwrap()]);
+        self.ffmpeg(&args)?;
         debug_print(format!("{video_name:?} successed"));
         Ok(())
     }
@@ -399,39 +1029,158 @@ impl BigImg<'_> {
     /// 生成中间部分的视频。
     ///
     /// # Parameters
-    /// - `len`: 素材图片中 `chunk` 数量。
+    /// - `scroll`: 滚动距离（像素）。
+    /// - `start`: 素材图片中第一个 `chunk` 的序号。
+    /// - `elapsed`: 该部分视频开始时在整个视频中的时间（秒）。
     /// - `pic_name`: 素材图片名称。
     /// - `video_name`: 生成视频名称。
+    /// - `crossfades`: 需要叠加的轮播图片。
+    ///
+    /// # Results
+    /// 返回生成视频的帧数。
     ///
     /// # Errors
     /// - 如果 `FFmpeg` 命令执行失败，则返回 `Err`。
     ///
-    fn generate_mid_video(&self, len: u32, pic_name: &Path, video_name: &Path) -> Result<()> {
-        let adjust_len = len - self.overlap;
-        let run_seconds = self.video_swip_speed * adjust_len + 1;
-        let speed = self.width_chunk / self.video_swip_speed;
+    fn generate_mid_video(
+        &self,
+        scroll: u32,
+        start: usize,
+        elapsed: f32,
+        pic_name: &Path,
+        video_name: &Path,
+        crossfades: &[Crossfade],
+    ) -> Result<u64> {
+        let frames = self.scroll_frames(scroll);
+        let seconds = self.frames_to_seconds(frames);
+        let speed = self.speed_expr();
 
-        self.ffmpeg(&[
+        let part = Part::Scroll {
+            start,
+            scroll,
+            elapsed,
+            seconds,
+        };
+        let (inputs, graph) =
+            self.filter_graph(&format!("x=-t*{speed}:shortest=1"), part, crossfades);
+        // 输入多留一秒，输出按帧数截断
+        let input_seconds = (seconds.ceil() as u32 + 1).to_string();
+        let mut args = vec![
             "-r",
             "1",
             "-loop",
             "1",
             "-t",
-            &run_seconds.to_string(),
+            &input_seconds,
             "-i",
             pic_name.to_str().unwrap(),
-            "-filter_complex",
-            &format!(
-                "color={}:s={}x{}:r={}[bg];[bg][0]overlay=x=-t*{speed}:shortest=1",
-                self.video_background_color, self.screen.0, self.screen.1, self.video_fps
-            ),
-            "-preset",
-            "fast",
-            "-y",
-            video_name.to_str().unwrap(),
-        ])?;
+        ];
+        args.extend(inputs.iter().map(String::as_str));
+        args.extend(graph.iter().map(String::as_str));
+        let encoding = self.encoding.args();
+        args.extend(encoding.iter().map(String::as_str));
+        let frames_arg = frames.to_string();
+        args.extend(["-frames:v", &frames_arg, "-y", video_name.to_str().unwrap()]);
+        self.ffmpeg(&args)?;
         debug_print(format!("{video_name:?} successed"));
-        Ok(())
+        Ok(frames)
+    }
+
+    /// 构建各部分视频共用的滤镜图，并叠加水印、计数器等固定在屏幕上的元素。
+    ///
+    /// # Parameters
+    /// - `overlay`: 素材图片叠加到背景上的 `overlay` 参数。
+    /// - `part`: 当前生成的视频部分。
+    /// - `crossfades`: 需要叠加的轮播图片，位于水印等元素之下。
+    ///
+    /// # Results
+    /// 返回额外输入参数和滤镜参数。
+    ///
+    fn filter_graph(
+        &self,
+        overlay: &str,
+        part: Part,
+        crossfades: &[Crossfade],
+    ) -> (Vec<String>, Vec<String>) {
+        let mut graph = FilterGraph::new(
+            &self.video_background_color,
+            self.screen,
+            self.video_fps,
+            overlay,
+        );
+        let (x0, speed, elapsed, duration) = match part {
+            Part::Cover => (0, 0.0, 0.0, self.video_cover_time as f32),
+            Part::Scroll {
+                elapsed, seconds, ..
+            } => (0, self.speed(), elapsed, seconds),
+            Part::Ending { elapsed, offset } => {
+                (offset, 0.0, elapsed, self.video_ending_time as f32)
+            }
+        };
+        for crossfade in crossfades {
+            crossfade.apply(&mut graph, x0, speed, elapsed, duration);
+        }
+        if let Some(ref watermark) = self.watermark {
+            watermark.apply(&mut graph, matches!(part, Part::Cover));
+        }
+        if let Some(ref counter) = self.counter {
+            let (x, range) = self.screen_x(part);
+            let index = self.index_expr(&x, range);
+            let total = self.chunks.iter().filter(|c| !c.is_divider()).count();
+            counter.apply(&mut graph, &x, &index, total, self.max_x());
+        }
+        graph.build()
+    }
+
+    /// 屏幕左边缘在整条长图中横坐标的表达式及其取值范围
+    ///
+    /// 滚动部分的范围只到本段的滚动距离，即下一段开始的位置。
+    fn screen_x(&self, part: Part) -> (String, RangeInclusive<u32>) {
+        match part {
+            Part::Cover => (String::from("0"), 0..=0),
+            Part::Scroll { start, scroll, .. } => {
+                let x0 = self.offsets(&self.chunks[..start])[start];
+                (format!("{x0}+t*{}", self.speed_expr()), x0..=x0 + scroll)
+            }
+            Part::Ending { .. } => {
+                let max_x = self.max_x();
+                (max_x.to_string(), max_x..=max_x)
+            }
+        }
+    }
+
+    /// 由屏幕左边缘横坐标表达式计算屏幕中央图像块序号的表达式
+    ///
+    /// 图像块宽度不一，序号为屏幕中央已越过的图像块左边缘数，不含第一个图像块。
+    /// 分隔块不计数，屏幕中央位于分隔块上时显示前一个图像块的序号。
+    /// 只有 `range` 内可能越过的边界才生成比较项，其余边界计入常数部分。
+    ///
+    /// # Parameters
+    /// - `x`: 屏幕左边缘横坐标的表达式。
+    /// - `range`: 横坐标的取值范围。
+    ///
+    fn index_expr(&self, x: &str, range: RangeInclusive<u32>) -> String {
+        let half = self.screen.0 / 2;
+        let offsets = self.offsets(self.chunks);
+        let bounds: Vec<u32> = self
+            .chunks
+            .iter()
+            .zip(&offsets)
+            .filter(|(chunk, _)| !chunk.is_divider())
+            .skip(1)
+            .map(|(_, &o)| o)
+            .collect();
+        let (lo, hi) = (range.start() + half, range.end() + half);
+        let mut expr = bounds.iter().filter(|&&o| o <= lo).count().to_string();
+        for o in bounds.iter().filter(|&&o| o > lo && o <= hi) {
+            expr.push_str(&format!("+gte({x}+{half},{o})"));
+        }
+        expr
+    }
+
+    /// 屏幕左边缘在整条长图中横坐标的最大值，即整个视频的滚动距离。
+    fn max_x(&self) -> u32 {
+        self.offsets(self.chunks)[self.chunks.len()].saturating_sub(self.screen.0)
     }
 
     #[allow(unused)]
@@ -448,7 +1197,7 @@ impl BigImg<'_> {
     /// - 无法执行ffmpeg命令时返回IO错误
     /// - ffmpeg进程返回非零状态码时打印stderr到控制台并返回Other类型错误
     ///
-    fn ffmpeg(&self, args: &[&str]) -> Result<()> {
+    fn ffmpeg<S: AsRef<OsStr>>(&self, args: &[S]) -> Result<()> {
         let command = Command::new("ffmpeg")
             .current_dir(&self.work_dir)
             .args(args)
@@ -463,20 +1212,22 @@ impl BigImg<'_> {
     /// 合并多个文件为单个输出文件，使用ffmpeg的concat协议
     ///
     /// # Parameters
-    /// - `results`: 需要合并的源文件路径列表
+    /// - `results`: 需要合并的视频片段列表
     /// - `save_name`: 合并后的输出文件路径
     ///
+    /// 导出 `OpenTimelineIO` 时间线时保留视频片段，只删除素材图片。
+    ///
     /// # Errors
     /// - 如果文件写入或 `FFmpeg` 命令执行失败，则返回 `Err`。
     ///
-    fn combain(&self, results: &mut [PathBuf], save_name: &Path) -> Result<()> {
+    fn combain(&self, results: &[Clip], save_name: &Path) -> Result<()> {
         // 构建ffmpeg concat协议要求的输入文件列表字符串
         // 格式示例：file '/path/to/file1'\nfile '/path/to/file2'
         let result_str =
             results
                 .iter()
                 .fold(String::with_capacity(results.len() * 20), |mut init, s| {
-                    init.push_str(&format!("file {}\n", s.to_string_lossy()));
+                    init.push_str(&format!("file {}\n", s.name.to_string_lossy()));
                     init
                 });
 
@@ -489,16 +1240,32 @@ impl BigImg<'_> {
         // -i 输入文件列表
         // -c copy 使用流拷贝模式（不重新编码）
         // -y 覆盖输出文件
-        self.ffmpeg(&[
-            "-f",
-            "concat",
-            "-i",
-            &list_file.to_string_lossy(),
-            "-c",
-            "copy",
-            "-y",
-            &save_name.to_string_lossy(),
-        ])?;
+        let mut args = vec![
+            String::from("-f"),
+            String::from("concat"),
+            String::from("-i"),
+            list_file.to_string_lossy().into_owned(),
+        ];
+
+        // 有分组分隔块时写入章节
+        let chapters = self.chapters();
+        let chapter_file = self.work_dir.join("chapters.txt");
+        if !chapters.is_empty() {
+            std::fs::write(&chapter_file, chapter::ffmetadata(&chapters))?;
+            args.extend(
+                [
+                    "-i",
+                    &chapter_file.to_string_lossy(),
+                    "-map",
+                    "0",
+                    "-map_chapters",
+                    "1",
+                ]
+                .map(String::from),
+            );
+        }
+        args.extend(["-c", "copy", "-y", &save_name.to_string_lossy()].map(String::from));
+        self.ffmpeg(&args)?;
 
         println!("{} successed", save_name.to_string_lossy());
 
@@ -506,10 +1273,12 @@ impl BigImg<'_> {
         // 1. 删除文件列表
         // 2. 删除所有中间结果文件及其对应的png文件
         let _ = std::fs::remove_file(&list_file);
+        let _ = std::fs::remove_file(&chapter_file);
         for result in results {
-            let _ = std::fs::remove_file(self.work_dir.join(&result));
-            result.set_extension("png");
-            let _ = std::fs::remove_file(self.work_dir.join(result));
+            if self.otio.is_none() {
+                let _ = std::fs::remove_file(self.work_dir.join(&result.name));
+            }
+            let _ = std::fs::remove_file(self.work_dir.join(result.name.with_extension("png")));
         }
         println!("cleanup successed");
         Ok(())
@@ -521,23 +1290,166 @@ impl Debug for BigImg<'_> {
         f.debug_struct("BigImg")
             .field("work_dir", &self.work_dir)
             .field("len_chunks", &self.chunks.len())
-            .field("chunk1", &self.chunks[0])
+            .field("chunk1", &self.chunks.first())
             .field("screen", &self.screen)
             .field("step", &self.step)
             .field("width_chunk", &self.width_chunk)
-            .field("overlap", &self.overlap)
-            .field("text_background_color", &self.text_background_color)
             .field("text_color", &self.text_color)
             .field("max_scale", &self.max_scale)
+            .field("min_scale", &self.min_scale)
+            .field("overflow", &self.overflow)
             .field("pic_h", &self.pic_h)
-            .field("text_up_h", &self.text_up_h)
-            .field("text_down_h", &self.text_down_h)
+            .field("sections", &self.sections)
             .field("font", &self.font)
+            .field("radius", &self.radius)
+            .field("line_height", &self.line_height)
+            .field("letter_spacing", &self.letter_spacing)
+            .field("markup", &self.markup)
             .field("video_cover_time", &self.video_cover_time)
             .field("video_ending_time", &self.video_ending_time)
             .field("video_background_color", &self.video_background_color)
             .field("video_swip_speed", &self.video_swip_speed)
             .field("video_fps", &self.video_fps)
+            .field("watermark", &self.watermark)
+            .field("counter", &self.counter)
+            .field("encoding", &self.encoding)
+            .field("factor", &self.factor)
+            .field("otio", &self.otio)
+            .field("srt", &self.srt)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(widths: &[f32]) -> Vec<Chunk> {
+        widths
+            .iter()
+            .map(|&w| {
+                let json = format!(
+                    r#"{{"pic_path": "a.png", "width": {w}, "text_up": ["a"], "text_down": ["b"]}}"#
+                );
+                serde_json::from_str(&json).unwrap()
+            })
+            .collect()
+    }
+
+    fn ranges(si: &BigImg) -> Vec<(Range<usize>, Option<usize>)> {
+        si.divide().into_iter().map(|s| (s.range, s.next)).collect()
+    }
+
+    #[test]
+    fn test_divide() {
+        // 等宽时与按数量重叠分段一致
+        let data = chunks(&[1.0; 10]);
+        let mut si = BigImg::new_with_default(Path::new("."), &data);
+        si.step(6);
+        assert_eq!(
+            ranges(&si),
+            vec![(0..6, Some(2)), (2..8, Some(4)), (4..10, None)]
+        );
+
+        // 宽图像块使分段变长，下一段从铺满一屏的位置开始
+        let data = chunks(&[1.0, 2.0, 1.0, 1.0, 1.0, 1.0, 1.0]);
+        let mut si = BigImg::new_with_default(Path::new("."), &data);
+        si.step(3);
+        assert_eq!(
+            ranges(&si),
+            vec![(0..4, Some(1)), (1..6, Some(2)), (2..7, None)]
+        );
+        assert_eq!(si.max_x(), 3840 - 1920);
+        assert_eq!(si.index_expr("0", 0..=0), "1");
+    }
+
+    #[test]
+    fn test_empty_chunks() {
+        // 查询没有匹配的图像块时拒绝渲染
+        let si = BigImg::new_with_default(Path::new("."), &[]);
+        assert!(format!("{si:?}").contains("chunk1: None"));
+        let targets = [OutputTarget::new("result.mp4", (1920, 1080), 60)];
+        assert!(si.run(&targets).is_err());
+    }
+
+    #[test]
+    fn test_text_up_h() {
+        let data = chunks(&[1.0]);
+        let mut si = BigImg::new_with_default(Path::new("."), &data);
+        si.text_up_h(300);
+        assert_eq!(section::heights(&si.sections, 1080 - 520), vec![300, 260]);
+    }
+
+    #[test]
+    fn test_fallback_font_data() {
+        let data = chunks(&[1.0]);
+        let mut si = BigImg::new_with_default(Path::new("."), &data);
+        assert!(si.fallback_font_data(b"not a font".as_slice()).is_err());
+        si.fallback_font_data(include_bytes!("../MiSans-Demibold.ttf").as_slice())
+            .unwrap();
+        assert_eq!(si.font.fonts().len(), 2);
+    }
+
+    #[test]
+    fn test_scroll_frames() {
+        let data = chunks(&[1.0; 10]);
+        let mut si = BigImg::new_with_default(Path::new("."), &data);
+
+        // 每 3 秒滑过 480 像素，滑过 960 像素正好 6 秒，不多出一秒
+        assert_eq!(si.scroll_frames(960), 360);
+        assert_eq!(si.frames_to_seconds(360), 6.0);
+        // 不足一帧的部分向上取整到帧
+        assert_eq!(si.scroll_frames(100), 38);
+        assert_eq!(si.scroll_frames(0), 1);
+
+        si.video_fps(30);
+        assert_eq!(si.scroll_frames(100), 19);
+    }
+
+    #[test]
+    fn test_counter_expr() {
+        let data = chunks(&[1.0; 10]);
+        let mut si = BigImg::new_with_default(Path::new("."), &data);
+        si.step(6);
+        let segments = si.divide();
+        let offsets = si.offsets(&data);
+        let scroll = si.scroll(&segments[0], &offsets);
+
+        // 滚动范围只到下一段开始的位置，不越过本段
+        let part = Part::Scroll {
+            start: 0,
+            scroll,
+            elapsed: 3.0,
+            seconds: si.frames_to_seconds(si.scroll_frames(scroll)),
+        };
+        let (x, range) = si.screen_x(part);
+        assert_eq!(x, "0+t*480/3");
+        assert_eq!(range, 0..=offsets[segments[0].next.unwrap()]);
+
+        // 屏幕中央从 960 滑到 1920，只比较其间的两条边界
+        let index = si.index_expr(&x, range);
+        assert_eq!(index, "2+gte(0+t*480/3+960,1440)+gte(0+t*480/3+960,1920)");
+
+        // 结尾时屏幕中央为倒数第二个图像块
+        let (x, range) = si.screen_x(Part::Ending {
+            elapsed: 0.0,
+            offset: 0,
+        });
+        assert_eq!(x, si.max_x().to_string());
+        assert_eq!(si.index_expr(&x, range), "8");
+
+        // 分隔块不计入边界，屏幕中央分别为第 2 个和第 5 个普通图像块
+        let mut data = chunks(&[1.0; 6]);
+        data.insert(0, Chunk::divider("A"));
+        data.insert(3, Chunk::divider("B"));
+        let divided = BigImg::new_with_default(Path::new("."), &data);
+        assert_eq!(divided.index_expr("0", 0..=0), "1");
+        let max_x = divided.max_x();
+        assert_eq!(divided.index_expr(&max_x.to_string(), max_x..=max_x), "4");
+
+        // 速度不能整除时保留分数
+        si.video_swip_speed(7);
+        assert_eq!(si.screen_x(part).0, "0+t*480/7");
+        assert!((si.speed() - 480.0 / 7.0).abs() < 1e-4);
+    }
+}
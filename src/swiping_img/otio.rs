@@ -0,0 +1,179 @@
+use crate::{
+    err_new, err_new_io,
+    error::{Kind, Result},
+};
+use serde_json::{json, Value};
+use std::{
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+/// 已生成的视频片段
+///
+/// # Parameters
+///
+/// * `name`: 片段文件名，相对于工作路径。
+/// * `frames`: 片段时长（帧）。
+#[derive(Clone, Debug)]
+pub(crate) struct Clip {
+    pub(crate) name: PathBuf,
+    pub(crate) frames: u64,
+}
+
+/// `RationalTime`，以帧为单位
+fn rational_time(frames: u64, fps: u32) -> Value {
+    json!({
+        "OTIO_SCHEMA": "RationalTime.1",
+        "rate": f64::from(fps),
+        "value": frames as f64,
+    })
+}
+
+/// 从 0 开始、长度为 `frames` 帧的 `TimeRange`
+fn time_range(frames: u64, fps: u32) -> Value {
+    json!({
+        "OTIO_SCHEMA": "TimeRange.1",
+        "start_time": rational_time(0, fps),
+        "duration": rational_time(frames, fps),
+    })
+}
+
+/// 引用外部媒体文件的片段
+fn clip(name: &str, target_url: &Path, frames: u64, fps: u32) -> Value {
+    json!({
+        "OTIO_SCHEMA": "Clip.1",
+        "name": name,
+        "metadata": {},
+        "source_range": time_range(frames, fps),
+        "effects": [],
+        "markers": [],
+        "media_reference": {
+            "OTIO_SCHEMA": "ExternalReference.1",
+            "name": "",
+            "metadata": {},
+            "target_url": target_url.to_string_lossy(),
+            "available_range": time_range(frames, fps),
+        },
+    })
+}
+
+fn track(name: &str, kind: &str, children: Vec<Value>) -> Value {
+    json!({
+        "OTIO_SCHEMA": "Track.1",
+        "name": name,
+        "kind": kind,
+        "metadata": {},
+        "source_range": null,
+        "effects": [],
+        "markers": [],
+        "children": children,
+    })
+}
+
+/// 构建 `OpenTimelineIO` 时间线
+///
+/// 视频轨道依次引用封面、各滚动分段和结尾片段，片段之间为硬切，与成片的拼接方式一致；
+/// 每个音频文件单独占一条音频轨道，长度与整个视频相同。
+///
+/// # Parameters
+/// - `name`: 时间线名称。
+/// - `fps`: 视频帧率。
+/// - `work_dir`: 片段所在的工作路径。
+/// - `clips`: 视频片段列表，依次为封面、各滚动分段和结尾。
+/// - `audio`: 音频文件列表。
+///
+pub(crate) fn timeline(
+    name: &str,
+    fps: u32,
+    work_dir: &Path,
+    clips: &[Clip],
+    audio: &[PathBuf],
+) -> Value {
+    let video = clips
+        .iter()
+        .map(|c| {
+            clip(
+                &c.name.to_string_lossy(),
+                &work_dir.join(&c.name),
+                c.frames,
+                fps,
+            )
+        })
+        .collect();
+    let total = clips.iter().map(|c| c.frames).sum();
+
+    let mut tracks = vec![track("Video", "Video", video)];
+    tracks.extend(audio.iter().enumerate().map(|(i, path)| {
+        let track_name = format!("Audio {}", i + 1);
+        let name = path
+            .file_name()
+            .map_or_else(|| track_name.clone(), |n| n.to_string_lossy().into_owned());
+        track(&track_name, "Audio", vec![clip(&name, path, total, fps)])
+    }));
+
+    json!({
+        "OTIO_SCHEMA": "Timeline.1",
+        "name": name,
+        "metadata": {},
+        "global_start_time": null,
+        "tracks": {
+            "OTIO_SCHEMA": "Stack.1",
+            "name": "tracks",
+            "metadata": {},
+            "source_range": null,
+            "effects": [],
+            "markers": [],
+            "children": tracks,
+        },
+    })
+}
+
+/// 将时间线写入 `.otio` 文件
+///
+/// # Errors
+/// - 如果文件创建或写入失败，则返回 `Err`。
+///
+pub(crate) fn write(save_name: &Path, timeline: &Value) -> Result<()> {
+    let writer = File::create(save_name).map_err(|e| err_new_io!(e))?;
+    serde_json::to_writer_pretty(writer, timeline)
+        .map_err(|e| err_new!(Kind::Other, &e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeline() {
+        let clip = |name: &str, frames: u64| Clip {
+            name: PathBuf::from(name),
+            frames,
+        };
+        let clips = [
+            clip("cover.mp4", 90),
+            clip("00.mp4", 300),
+            clip("ending.mp4", 90),
+        ];
+        let audio = [PathBuf::from("bgm.mp3"), PathBuf::new()];
+
+        // 片段依次排列，之间为硬切
+        let value = timeline("result", 30, Path::new("work"), &clips, &audio);
+        let tracks = &value["tracks"]["children"];
+        assert_eq!(tracks[0]["kind"], "Video");
+        let video = tracks[0]["children"].as_array().unwrap();
+        assert_eq!(video.len(), 3);
+        assert!(video.iter().all(|v| v["OTIO_SCHEMA"] == "Clip.1"));
+        assert_eq!(
+            tracks[0]["children"][1]["source_range"]["duration"]["value"],
+            300.0
+        );
+        assert_eq!(tracks[1]["kind"], "Audio");
+        assert_eq!(
+            tracks[1]["children"][0]["source_range"]["duration"]["value"],
+            480.0
+        );
+        // 没有文件名的音频以轨道名命名
+        assert_eq!(tracks[2]["name"], "Audio 2");
+        assert_eq!(tracks[2]["children"][0]["name"], "Audio 2");
+    }
+}
@@ -0,0 +1,459 @@
+use super::{group::Grouping, Chunk};
+use crate::{
+    err_new, err_new_io,
+    error::{Error, Kind, Result},
+};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, fmt, fs::File, path::Path, str::FromStr};
+
+/// 比较运算符
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Le,
+    Ge,
+    Lt,
+    Gt,
+    Contains,
+}
+
+impl Op {
+    /// 按匹配优先级排列，两个字符的运算符在前
+    const ALL: [(Op, &'static str); 7] = [
+        (Op::Eq, "=="),
+        (Op::Ne, "!="),
+        (Op::Le, "<="),
+        (Op::Ge, ">="),
+        (Op::Lt, "<"),
+        (Op::Gt, ">"),
+        (Op::Contains, "~"),
+    ];
+
+    fn symbol(self) -> &'static str {
+        Self::ALL
+            .iter()
+            .find(|(op, _)| *op == self)
+            .map_or("", |p| p.1)
+    }
+}
+
+/// 筛选条件
+///
+/// 写作 `字段 运算符 值`，如 `rarity>=5`、`text_down~生日`。
+/// 运算符为 `==`、`!=`、`<`、`<=`、`>`、`>=` 和包含 `~`，
+/// 大小比较与排序规则相同，见 [`compare`]。
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Filter {
+    field: String,
+    op: Op,
+    value: String,
+}
+
+impl Filter {
+    fn matches(&self, chunk: &Chunk) -> bool {
+        let Some(text) = field(chunk, &self.field) else {
+            return self.op == Op::Ne;
+        };
+        let ord = compare(&text, &self.value);
+        match self.op {
+            Op::Eq => ord == Ordering::Equal,
+            Op::Ne => ord != Ordering::Equal,
+            Op::Le => ord != Ordering::Greater,
+            Op::Ge => ord != Ordering::Less,
+            Op::Lt => ord == Ordering::Less,
+            Op::Gt => ord == Ordering::Greater,
+            Op::Contains => text.contains(&self.value),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // 取最靠前的运算符，位置相同时取较长的
+        let (index, op, symbol) = Op::ALL
+            .iter()
+            .filter_map(|&(op, symbol)| s.find(symbol).map(|i| (i, op, symbol)))
+            .min_by_key(|&(i, _, symbol)| (i, std::cmp::Reverse(symbol.len())))
+            .ok_or_else(|| err_new!(Kind::Other, &format!("Invalid filter: {s}")))?;
+        let field = s[..index].trim();
+        if field.is_empty() {
+            return Err(err_new!(Kind::Other, &format!("Invalid filter: {s}")));
+        }
+        Ok(Self {
+            field: field.to_string(),
+            op,
+            value: s[index + symbol.len()..].trim().to_string(),
+        })
+    }
+}
+
+impl TryFrom<String> for Filter {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<Filter> for String {
+    fn from(value: Filter) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.field, self.op.symbol(), self.value)
+    }
+}
+
+/// 图像块列表的查询
+///
+/// 依次筛选、排序、反转，再跳过 `offset` 个并最多保留 `limit` 个，最后按 `group` 插入分隔块。
+/// 缺少排序字段的图像块无论是否反转都排在最后。
+/// 可以写在 JSON 配置中，也可以由命令行参数构建，见 [`Query::parse_args`]。
+///
+/// # Parameters
+///
+/// * `filter`: 筛选条件，需全部满足。
+/// * `sort`: 排序字段，为 `None` 时保持原顺序。
+/// * `reverse`: 是否反转顺序。
+/// * `offset`: 跳过的图像块数量。
+/// * `limit`: 最多保留的图像块数量，为 `None` 时不限制。
+/// * `group`: 分组方式，为 `None` 时不分组。
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Query {
+    filter: Vec<Filter>,
+    sort: Option<String>,
+    reverse: bool,
+    offset: usize,
+    limit: Option<usize>,
+    group: Option<Grouping>,
+}
+
+impl Query {
+    /// 添加筛选条件
+    pub fn filter(&mut self, filter: Filter) -> &mut Self {
+        self.filter.push(filter);
+        self
+    }
+
+    /// 设置排序字段
+    pub fn sort(&mut self, field: impl Into<String>) -> &mut Self {
+        self.sort = Some(field.into());
+        self
+    }
+
+    /// 设置是否反转顺序
+    pub fn reverse(&mut self, reverse: bool) -> &mut Self {
+        self.reverse = reverse;
+        self
+    }
+
+    /// 设置跳过的数量
+    pub fn offset(&mut self, offset: usize) -> &mut Self {
+        self.offset = offset;
+        self
+    }
+
+    /// 设置最多保留的数量
+    pub fn limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// 设置分组方式
+    pub fn group(&mut self, group: Grouping) -> &mut Self {
+        self.group = Some(group);
+        self
+    }
+
+    /// 从 JSON 文件读取查询
+    ///
+    /// # Errors
+    /// - 如果文件打开失败或不是有效的查询，则返回 `Err`。
+    ///
+    pub fn read<P: AsRef<Path>>(file: P) -> Result<Self> {
+        let file = File::open(file.as_ref()).map_err(|e| err_new_io!(e))?;
+        serde_json::from_reader(file).map_err(|e| err_new!(Kind::Other, &e.to_string()))
+    }
+
+    /// 由命令行参数构建查询
+    ///
+    /// 支持 `--query <file>`、`--filter <条件>`（可重复）、`--sort <字段>`、
+    /// `--reverse`、`--offset <n>`、`--limit <n|all>` 和 `--group <字段[:year|month]>`，
+    /// 命令行中的条件追加到查询文件之后，其余选项覆盖查询文件。`--limit all` 取消数量限制。
+    ///
+    /// # Errors
+    /// - 如果参数未知、缺少值或值无效，则返回 `Err`。
+    ///
+    pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut query = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| err_new!(Kind::Other, &format!("Missing value for {arg}")))
+            };
+            let number = |v: String| {
+                v.parse::<usize>()
+                    .map_err(|e| err_new!(Kind::Other, &format!("{v}: {e}")))
+            };
+            match arg.as_str() {
+                "--query" => {
+                    let filter = std::mem::take(&mut query.filter);
+                    query = Self::read(value()?)?;
+                    query.filter.extend(filter);
+                }
+                "--filter" => {
+                    query.filter(value()?.parse()?);
+                }
+                "--sort" => {
+                    query.sort(value()?);
+                }
+                "--reverse" => {
+                    query.reverse(true);
+                }
+                "--offset" => {
+                    query.offset(number(value()?)?);
+                }
+                "--limit" => match value()?.as_str() {
+                    "all" => query.limit = None,
+                    v => {
+                        query.limit(number(v.to_string())?);
+                    }
+                },
+                "--group" => {
+                    query.group(value()?.parse()?);
+                }
+                _ => return Err(err_new!(Kind::Other, &format!("Unknown argument: {arg}"))),
+            }
+        }
+        Ok(query)
+    }
+
+    /// 对图像块列表执行查询
+    #[must_use]
+    pub fn apply(&self, chunks: Vec<Chunk>) -> Vec<Chunk> {
+        let mut chunks: Vec<Chunk> = chunks
+            .into_iter()
+            .filter(|c| self.filter.iter().all(|f| f.matches(c)))
+            .collect();
+        if let Some(ref key) = self.sort {
+            // 缺少排序字段的图像块排在最后
+            chunks.sort_by_cached_key(|c| SortKey(field(c, key)));
+            if self.reverse {
+                // 只反转有排序字段的部分，缺少字段的仍在最后
+                let present = chunks.partition_point(|c| field(c, key).is_some());
+                chunks[..present].reverse();
+            }
+        } else if self.reverse {
+            chunks.reverse();
+        }
+        let chunks = chunks
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+        match self.group {
+            Some(ref group) => group.apply(chunks),
+            None => chunks,
+        }
+    }
+}
+
+struct SortKey(Option<String>);
+
+impl PartialEq for SortKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortKey {}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => compare(a, b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+/// 图像块中字段的文本
+///
+/// 先查找附加字段，再查找同名文本区域，多行文本以换行连接。
+pub(crate) fn field(chunk: &Chunk, name: &str) -> Option<String> {
+    if let Some(value) = chunk.metadata(name) {
+        return Some(match value {
+            serde_json::Value::String(s) => s.clone(),
+            v => v.to_string(),
+        });
+    }
+    let text = chunk.text(name);
+    (!text.is_empty()).then(|| text.join("\n"))
+}
+
+/// 值的比较形式
+#[derive(PartialEq, PartialOrd)]
+enum Key<'a> {
+    Number(f64),
+    Date(i32, u32, u32),
+    Text(&'a str),
+}
+
+/// 比较两个值
+///
+/// 都是数字时按数值比较；都含有日期（如 `2023-05-01`、`2023年5月1日`、`5月1日`）时
+/// 按第一个日期比较；否则按字符串比较。不同类型之间数字在前、日期其次、字符串最后。
+/// `NaN`、`inf` 等非有限值按字符串比较，保证排序的全序关系。
+pub fn compare(a: &str, b: &str) -> Ordering {
+    fn key(s: &str) -> Key<'_> {
+        if let Some(n) = s.trim().parse::<f64>().ok().filter(|n| n.is_finite()) {
+            return Key::Number(n);
+        }
+        match find_date(s) {
+            Some((y, m, d)) => Key::Date(y, m, d),
+            None => Key::Text(s),
+        }
+    }
+    key(a).partial_cmp(&key(b)).unwrap_or(Ordering::Equal)
+}
+
+/// 查找字符串中的第一个日期，没有年份时年份为 `0`
+pub(crate) fn find_date(s: &str) -> Option<(i32, u32, u32)> {
+    // 所有连续数字及其后的第一个字符
+    let mut runs: Vec<(&str, Option<char>)> = Vec::new();
+    let mut rest = s;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        let digits = &rest[start..];
+        let end = digits
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(digits.len());
+        runs.push((&digits[..end], digits[end..].chars().next()));
+        rest = &digits[end..];
+    }
+
+    let valid = |m: u32, d: u32| (1..=12).contains(&m) && (1..=31).contains(&d);
+    for (i, &(num, sep)) in runs.iter().enumerate() {
+        let next = |k: usize| runs.get(i + k).and_then(|r| r.0.parse::<u32>().ok());
+        match sep {
+            Some('-' | '/' | '.' | '年') if num.len() == 4 => {
+                let sep2 = runs.get(i + 1).and_then(|r| r.1);
+                if let (Some(m), Some('-' | '/' | '.' | '月'), Some(d)) = (next(1), sep2, next(2))
+                {
+                    if valid(m, d) {
+                        return Some((num.parse().ok()?, m, d));
+                    }
+                }
+            }
+            Some('月') => {
+                if let (Ok(m), Some(d)) = (num.parse::<u32>(), next(1)) {
+                    if valid(m, d) {
+                        return Some((0, m, d));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(name: &str, date: &str, rarity: u32) -> Chunk {
+        Chunk::from_source(
+            Path::new("a.png"),
+            vec![name.to_string()],
+            vec![format!("上线: {date}")],
+        )
+        .with_metadata("rarity", rarity)
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(compare("9", "10"), Ordering::Less);
+        assert_eq!(compare("上线: 2023年12月1日", "2024-01-05"), Ordering::Less);
+        assert_eq!(compare("5月1日", "12月1日"), Ordering::Less);
+        assert_eq!(compare("b", "a"), Ordering::Greater);
+        // 非有限值不是数字，与数字比较时排在后面
+        assert_eq!(compare("NaN", "1"), Ordering::Greater);
+        assert_eq!(compare("NaN", "NaN"), Ordering::Equal);
+        assert_eq!(compare("inf", "nan"), Ordering::Less);
+        assert_eq!(find_date("生日: 10月23日"), Some((0, 10, 23)));
+    }
+
+    #[test]
+    fn test_apply() {
+        let chunks = vec![
+            chunk("a", "2023年05月01日", 6),
+            chunk("b", "2021年12月25日", 5),
+            chunk("c", "2022年01月10日", 6),
+            chunk("d", "2020年03月03日", 4),
+        ];
+        let query = Query::parse_args(
+            [
+                "--filter",
+                "rarity>=5",
+                "--sort",
+                "text_down",
+                "--reverse",
+                "--limit",
+                "2",
+            ]
+            .map(String::from),
+        )
+        .unwrap();
+        let titles: Vec<_> = query
+            .apply(chunks)
+            .iter()
+            .map(|c| c.title().to_string())
+            .collect();
+        assert_eq!(titles, vec!["a", "c"]);
+
+        // `--limit all` 取消之前的数量限制
+        let query =
+            Query::parse_args(["--limit", "1", "--limit", "all"].map(String::from)).unwrap();
+        assert_eq!(query, Query::default());
+        assert!(Query::parse_args(["--limit", "some"].map(String::from)).is_err());
+
+        // 反转时缺少排序字段的图像块仍排在最后
+        let chunks = vec![
+            chunk("a", "2023年05月01日", 6),
+            Chunk::from_source(Path::new("a.png"), vec![String::from("x")], vec![]),
+            chunk("b", "2021年12月25日", 5),
+            chunk("c", "NaN", 6).with_metadata("rarity", "NaN"),
+        ];
+        let query = Query::parse_args(["--sort", "rarity", "--reverse"].map(String::from)).unwrap();
+        let titles: Vec<_> = query
+            .apply(chunks)
+            .iter()
+            .map(|c| c.title().to_string())
+            .collect();
+        assert_eq!(titles, vec!["c", "a", "b", "x"]);
+
+        let filter: Filter = "text_up ~ b".parse().unwrap();
+        assert_eq!(filter.to_string(), "text_up~b");
+        let query: Query =
+            serde_json::from_str(r#"{"filter": ["rarity==6"], "offset": 1}"#).unwrap();
+        assert_eq!(query.filter.len(), 1);
+        assert!("no operator".parse::<Filter>().is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use crate::imageproc::drawing::{HAlign, TextStyle, VAlign};
+use image::Rgba;
+
+/// 图像块中的文本区域
+///
+/// 图片区域下方的空间按权重分配给各文本区域，自上而下排列。
+/// 图像块的文本按区域名称取用，没有对应文本的区域只绘制背景框。
+///
+/// # Parameters
+///
+/// * `name`: 区域名称，即图像块 JSON 中文本列表的键。
+/// * `weight`: 高度权重。
+/// * `background_color`: 背景框颜色。
+/// * `padding`: 区域底部不放置文本的留白高度。
+/// * `text_padding`: 每行文本框四周的留白。
+/// * `min_scale`: 该区域文本的最小字号，`None` 时使用 [`BigImg`](super::BigImg) 的最小字号。
+/// * `h_align`: 文本的水平对齐方式。
+/// * `v_align`: 文本的垂直对齐方式。
+#[derive(Clone, Debug)]
+pub struct Section {
+    name: String,
+    weight: f32,
+    background_color: Rgba<u8>,
+    padding: u32,
+    text_padding: u32,
+    min_scale: Option<f32>,
+    h_align: HAlign,
+    v_align: VAlign,
+}
+
+impl Section {
+    /// 默认布局中上方文本区域的名称
+    pub const UP: &'static str = "text_up";
+    /// 默认布局中下方文本区域的名称
+    pub const DOWN: &'static str = "text_down";
+
+    /// 创建文本区域
+    ///
+    /// # Parameters
+    /// - `name`: 区域名称。
+    /// - `weight`: 高度权重。
+    /// - `background_color`: 背景框颜色。
+    ///
+    /// # Panics
+    /// - 如果 `weight` 不是正数，程序将 panic
+    ///
+    #[must_use]
+    pub fn new(
+        name: impl Into<String>,
+        weight: f32,
+        background_color: impl Into<Rgba<u8>>,
+    ) -> Self {
+        assert!(weight > 0.0, "Section weight must be positive.");
+        Self {
+            name: name.into(),
+            weight,
+            background_color: background_color.into(),
+            padding: 0,
+            text_padding: 0,
+            min_scale: None,
+            h_align: HAlign::default(),
+            v_align: VAlign::default(),
+        }
+    }
+
+    /// 默认布局：上方标题区域和下方信息区域
+    #[must_use]
+    pub fn preset() -> Vec<Self> {
+        let mut down = Self::new(Self::DOWN, 346.0, [44, 85, 153, 255]);
+        down.padding(30);
+        vec![Self::new(Self::UP, 214.0, [23, 150, 235, 255]), down]
+    }
+
+    /// 设置底部留白高度
+    pub fn padding(&mut self, padding: u32) -> &mut Self {
+        self.padding = padding;
+        self
+    }
+
+    /// 设置每行文本框四周的留白，默认为 0
+    pub fn text_padding(&mut self, text_padding: u32) -> &mut Self {
+        self.text_padding = text_padding;
+        self
+    }
+
+    /// 设置该区域文本的最小字号，覆盖 [`BigImg::min_scale`](super::BigImg::min_scale)
+    ///
+    /// # Parameters
+    /// - `min_scale`: 最小字号，使用 `f32` 类型表示
+    ///
+    pub fn min_scale(&mut self, min_scale: f32) -> &mut Self {
+        self.min_scale = Some(min_scale);
+        self
+    }
+
+    /// 设置高度权重
+    ///
+    /// # Panics
+    /// - 如果 `weight` 不是正数，程序将 panic
+    ///
+    pub fn weight(&mut self, weight: f32) -> &mut Self {
+        assert!(weight > 0.0, "Section weight must be positive.");
+        self.weight = weight;
+        self
+    }
+
+    /// 设置背景框颜色
+    pub fn background_color(&mut self, background_color: impl Into<Rgba<u8>>) -> &mut Self {
+        self.background_color = background_color.into();
+        self
+    }
+
+    /// 设置文本的对齐方式，默认水平和垂直都居中
+    ///
+    /// # Parameters
+    /// - `h_align`: 水平对齐方式，`Justify` 时每行文本撑满区域宽度
+    /// - `v_align`: 垂直对齐方式
+    ///
+    pub fn align(&mut self, h_align: HAlign, v_align: VAlign) -> &mut Self {
+        self.h_align = h_align;
+        self.v_align = v_align;
+        self
+    }
+
+    /// 区域名称
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn color(&self) -> Rgba<u8> {
+        self.background_color
+    }
+
+    /// 区域中文本的排版样式
+    ///
+    /// 区域没有设置最小字号时使用 `min_scale`。
+    pub(crate) fn text_style(&self, max_scale: f32, min_scale: f32) -> TextStyle {
+        TextStyle::new(max_scale)
+            .align(self.h_align, self.v_align)
+            .padding(self.text_padding)
+            .min_scale(self.min_scale.unwrap_or(min_scale))
+    }
+
+    pub(crate) fn padding_px(&self) -> u32 {
+        self.padding
+    }
+
+    /// 按输出目标的缩放比例调整留白和最小字号
+    pub(crate) fn scale_by(&mut self, factor: f32) {
+        self.padding = (self.padding as f32 * factor).round() as u32;
+        self.text_padding = (self.text_padding as f32 * factor).round() as u32;
+        if let Some(ref mut min_scale) = self.min_scale {
+            *min_scale *= factor;
+        }
+    }
+}
+
+/// 按权重将 `total` 像素分配给各区域
+///
+/// 按累计权重取整，保证各区域高度之和恰好为 `total`。
+pub(crate) fn heights(sections: &[Section], total: u32) -> Vec<u32> {
+    let sum: f32 = sections.iter().map(|s| s.weight).sum();
+    let mut acc = 0.0;
+    let mut prev = 0;
+    sections
+        .iter()
+        .map(|s| {
+            acc += s.weight;
+            let end = (total as f32 * acc / sum).round() as u32;
+            let h = end - prev;
+            prev = end;
+            h
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heights() {
+        assert_eq!(heights(&Section::preset(), 560), vec![214, 346]);
+        let sections = vec![
+            Section::new("a", 1.0, [0, 0, 0, 255]),
+            Section::new("b", 1.0, [0, 0, 0, 255]),
+            Section::new("c", 1.0, [0, 0, 0, 255]),
+        ];
+        let h = heights(&sections, 100);
+        assert_eq!(h.iter().sum::<u32>(), 100);
+        assert_eq!(h, vec![33, 34, 33]);
+    }
+
+    #[test]
+    fn test_text_style() {
+        let mut section = Section::new("a", 1.0, [0, 0, 0, 255]);
+        let style = section.text_style(60.0, 24.0);
+        assert_eq!((style.padding, style.min_scale), (0, 24.0));
+
+        // 区域的留白和最小字号随输出目标缩放
+        section.text_padding(10).min_scale(30.0);
+        section.scale_by(0.5);
+        let style = section.text_style(60.0, 24.0);
+        assert_eq!((style.padding, style.min_scale), (5, 15.0));
+    }
+}
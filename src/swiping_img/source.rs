@@ -0,0 +1,225 @@
+use crate::{err_new_image, error::Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use image::{DynamicImage, GenericImageView, ImageFormat, ImageReader};
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fmt,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// 图片来源
+///
+/// JSON 中为字符串时，以 `data:` 开头的视为 base64 编码的 data URI，其余视为文件路径；
+/// 也可以写成 `{"base64": "..."}` 直接嵌入 base64 编码的图片文件内容。
+/// 内存中的图片序列化时编码为 PNG 格式的 data URI，空白图片序列化为空字符串。
+#[derive(Clone)]
+pub enum ImageSource {
+    /// 图片文件路径
+    Path(PathBuf),
+    /// 编码后的图片文件内容，如 PNG 或 JPEG 数据
+    Bytes(Arc<[u8]>),
+    /// 已解码的图片，如程序生成的图表
+    Image(Arc<DynamicImage>),
+    /// 空白图片，如分组分隔块
+    Blank,
+}
+
+impl ImageSource {
+    /// 文件路径，其他来源为 `None`
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            Self::Path(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn path_mut(&mut self) -> Option<&mut PathBuf> {
+        match self {
+            Self::Path(path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// 图片是否存在，只有文件路径可能不存在
+    #[must_use]
+    pub fn exists(&self) -> bool {
+        self.path().is_none_or(Path::exists)
+    }
+
+    /// 打开并解码图片
+    ///
+    /// # Errors
+    /// - 如果打开或解码图片时发生错误，则返回 `Err`。
+    ///
+    pub fn open(&self) -> Result<DynamicImage> {
+        match self {
+            Self::Path(path) => image::open(path).map_err(|e| err_new_image!(e)),
+            Self::Bytes(bytes) => image::load_from_memory(bytes).map_err(|e| err_new_image!(e)),
+            Self::Image(img) => Ok(DynamicImage::clone(img)),
+            Self::Blank => Ok(DynamicImage::new_rgba8(1, 1)),
+        }
+    }
+
+    /// 图片的宽度和高度，文件和编码后的数据只读取文件头，不解码像素
+    ///
+    /// # Errors
+    /// - 如果打开图片或识别格式时发生错误，则返回 `Err`。
+    ///
+    pub fn dimensions(&self) -> Result<(u32, u32)> {
+        match self {
+            Self::Path(path) => image::image_dimensions(path).map_err(|e| err_new_image!(e)),
+            Self::Bytes(bytes) => ImageReader::new(Cursor::new(bytes))
+                .with_guessed_format()
+                .map_err(|e| err_new_image!(e))?
+                .into_dimensions()
+                .map_err(|e| err_new_image!(e)),
+            Self::Image(img) => Ok(img.dimensions()),
+            Self::Blank => Ok((1, 1)),
+        }
+    }
+
+    /// 解析 data URI，只支持 base64 编码
+    fn from_data_uri(uri: &str) -> std::result::Result<Self, String> {
+        let (header, data) = uri
+            .split_once(',')
+            .ok_or_else(|| String::from("Invalid data URI"))?;
+        if !header.ends_with(";base64") {
+            return Err(String::from("Only base64 data URIs are supported"));
+        }
+        Self::from_base64(data)
+    }
+
+    fn from_base64(data: &str) -> std::result::Result<Self, String> {
+        STANDARD
+            .decode(data.trim())
+            .map(|bytes| Self::Bytes(bytes.into()))
+            .map_err(|e| e.to_string())
+    }
+
+    /// 编码为 data URI，内存中的图片编码为 PNG
+    fn to_data_uri(bytes: &[u8]) -> String {
+        let mime =
+            image::guess_format(bytes).map_or("application/octet-stream", |f| f.to_mime_type());
+        format!("data:{mime};base64,{}", STANDARD.encode(bytes))
+    }
+}
+
+impl From<PathBuf> for ImageSource {
+    fn from(value: PathBuf) -> Self {
+        Self::Path(value)
+    }
+}
+
+impl From<&Path> for ImageSource {
+    fn from(value: &Path) -> Self {
+        Self::Path(value.to_path_buf())
+    }
+}
+
+impl From<Vec<u8>> for ImageSource {
+    fn from(value: Vec<u8>) -> Self {
+        Self::Bytes(value.into())
+    }
+}
+
+impl From<DynamicImage> for ImageSource {
+    fn from(value: DynamicImage) -> Self {
+        Self::Image(Arc::new(value))
+    }
+}
+
+impl fmt::Display for ImageSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Path(path) => write!(f, "{}", path.display()),
+            Self::Bytes(bytes) => write!(f, "<embedded {} bytes>", bytes.len()),
+            Self::Image(img) => {
+                let (w, h) = img.dimensions();
+                write!(f, "<image {w}x{h}>")
+            }
+            Self::Blank => write!(f, "<blank>"),
+        }
+    }
+}
+
+impl fmt::Debug for ImageSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.to_string())
+    }
+}
+
+impl Serialize for ImageSource {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self {
+            Self::Path(path) => path.serialize(serializer),
+            Self::Bytes(bytes) => serializer.serialize_str(&Self::to_data_uri(bytes)),
+            Self::Image(img) => {
+                let mut bytes = Vec::new();
+                img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+                    .map_err(ser::Error::custom)?;
+                serializer.serialize_str(&Self::to_data_uri(&bytes))
+            }
+            Self::Blank => serializer.serialize_str(""),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ImageSource {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Text(String),
+            Base64 { base64: String },
+        }
+
+        match Repr::deserialize(deserializer)? {
+            Repr::Text(text) if text.starts_with("data:") => {
+                Self::from_data_uri(&text).map_err(de::Error::custom)
+            }
+            Repr::Text(text) if text.is_empty() => Ok(Self::Blank),
+            Repr::Text(text) => Ok(Self::Path(PathBuf::from(text))),
+            Repr::Base64 { base64 } => Self::from_base64(&base64).map_err(de::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serde() {
+        let img = DynamicImage::new_rgba8(2, 3);
+        let json = serde_json::to_string(&ImageSource::from(img)).unwrap();
+        assert!(json.starts_with(r#""data:image/png;base64,"#));
+
+        // data URI 和 base64 对象都能解码回原图
+        let source: ImageSource = serde_json::from_str(&json).unwrap();
+        assert_eq!(source.open().unwrap().dimensions(), (2, 3));
+        let data = json.trim_matches('"').split_once(',').unwrap().1;
+        let source: ImageSource =
+            serde_json::from_str(&format!(r#"{{"base64": "{data}"}}"#)).unwrap();
+        assert_eq!(source.open().unwrap().dimensions(), (2, 3));
+
+        let source: ImageSource = serde_json::from_str(r#""a/b.png""#).unwrap();
+        assert_eq!(source.path(), Some(Path::new("a/b.png")));
+        assert!(serde_json::from_str::<ImageSource>(r#""data:image/png,abc""#).is_err());
+    }
+
+    #[test]
+    fn test_dimensions() {
+        let mut bytes = Vec::new();
+        DynamicImage::new_rgba8(2, 3)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        // 只读取文件头，截断的像素数据不影响尺寸
+        let header = bytes[..bytes.len() - 12].to_vec();
+        assert_eq!(ImageSource::from(header).dimensions().unwrap(), (2, 3));
+        assert!(ImageSource::from(vec![1, 2, 3]).dimensions().is_err());
+        assert_eq!(ImageSource::Blank.dimensions().unwrap(), (1, 1));
+    }
+}
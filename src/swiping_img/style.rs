@@ -0,0 +1,428 @@
+use super::{BigImg, Section};
+use crate::imageproc::drawing::{
+    parse_markup, plain_runs, FontChain, Outline, Run, Shadow, TextEffect, TextStyle,
+};
+use crate::{
+    err_new, err_new_io,
+    error::{Kind, Result},
+};
+use image::Rgba;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// 单个图像块的样式覆盖
+///
+/// 未设置的字段沿用 `BigImg` 中的默认样式。
+///
+/// # Parameters
+///
+/// * `text_background_color`: 默认布局中上下两个文本区域的背景颜色。
+/// * `section_colors`: 按区域名称覆盖的背景颜色，优先于 `text_background_color`。
+/// * `text_color`: 文本颜色。
+/// * `max_scale`: 字体的最大缩放因子。
+/// * `font`: 字体文件路径，替换字体链中的主字体。
+/// * `radius`: 文本背景框的圆角半径。
+/// * `line_height`: 行高，为字体高度的倍数。
+/// * `letter_spacing`: 字间距，以字号为单位。
+/// * `text_outline`: 文本描边。
+/// * `text_shadow`: 文本阴影。
+/// * `markup`: 是否解析文本中的标记，见 [`parse_markup`]。
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ChunkStyle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text_background_color: Option<([u8; 4], [u8; 4])>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    section_colors: BTreeMap<String, [u8; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text_color: Option<[u8; 4]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_scale: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    font: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    radius: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    line_height: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    letter_spacing: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text_outline: Option<TextOutline>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text_shadow: Option<TextShadow>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    markup: Option<bool>,
+}
+
+/// 文本描边
+///
+/// # Parameters
+///
+/// * `width`: 描边宽度（像素）。
+/// * `color`: 描边颜色，默认为黑色。
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TextOutline {
+    pub width: f32,
+    #[serde(default = "black")]
+    pub color: [u8; 4],
+}
+
+/// 文本阴影
+///
+/// # Parameters
+///
+/// * `offset`: 阴影相对文本的偏移（水平，垂直），默认为 `(4, 4)`。
+/// * `blur`: 模糊半径（像素），默认为 `6.0`。
+/// * `opacity`: 不透明度，范围 `0.0..=1.0`，默认为 `0.6`。
+/// * `color`: 阴影颜色，默认为黑色。
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TextShadow {
+    #[serde(default = "default_offset")]
+    pub offset: (i32, i32),
+    #[serde(default = "default_blur")]
+    pub blur: f32,
+    #[serde(default = "default_opacity")]
+    pub opacity: f32,
+    #[serde(default = "black")]
+    pub color: [u8; 4],
+}
+
+fn black() -> [u8; 4] {
+    [0, 0, 0, 255]
+}
+
+fn default_offset() -> (i32, i32) {
+    (4, 4)
+}
+
+fn default_blur() -> f32 {
+    6.0
+}
+
+fn default_opacity() -> f32 {
+    0.6
+}
+
+impl From<TextOutline> for Outline<Rgba<u8>> {
+    fn from(outline: TextOutline) -> Self {
+        Self {
+            width: outline.width,
+            color: Rgba(outline.color),
+        }
+    }
+}
+
+impl From<TextShadow> for Shadow<Rgba<u8>> {
+    fn from(shadow: TextShadow) -> Self {
+        Self {
+            offset: shadow.offset,
+            blur: shadow.blur,
+            opacity: shadow.opacity,
+            color: Rgba(shadow.color),
+        }
+    }
+}
+
+/// 合并后实际使用的样式
+pub(crate) struct Style {
+    pub(crate) section_colors: Vec<Rgba<u8>>,
+    pub(crate) text_color: Rgba<u8>,
+    pub(crate) max_scale: f32,
+    pub(crate) font: FontChain,
+    pub(crate) line_height: f32,
+    pub(crate) letter_spacing: f32,
+    pub(crate) radius: i32,
+    pub(crate) effect: TextEffect<Rgba<u8>>,
+    pub(crate) markup: bool,
+}
+
+impl Style {
+    /// 文本中的各行，只有启用标记时才解析其中的标记，否则原样绘制
+    pub(crate) fn runs(&self, text: &str) -> Vec<Vec<Run<Rgba<u8>>>> {
+        if self.markup {
+            parse_markup(text)
+        } else {
+            plain_runs(text)
+        }
+    }
+
+    /// 为文本样式加上行高和字间距
+    pub(crate) fn spaced(&self, text_style: TextStyle) -> TextStyle {
+        text_style
+            .line_height(self.line_height)
+            .letter_spacing(self.letter_spacing)
+    }
+}
+
+impl ChunkStyle {
+    /// 是否没有覆盖任何样式
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 设置默认布局中上下两个文本区域的背景颜色
+    pub fn text_background_color(
+        &mut self,
+        color_up: impl Into<Rgba<u8>>,
+        color_down: impl Into<Rgba<u8>>,
+    ) -> &mut Self {
+        self.text_background_color = Some((color_up.into().0, color_down.into().0));
+        self
+    }
+
+    /// 设置某个文本区域的背景颜色，适用于任意布局
+    ///
+    /// # Parameters
+    /// - `name`: 文本区域名称
+    /// - `color`: 背景颜色
+    ///
+    pub fn section_color(
+        &mut self,
+        name: impl Into<String>,
+        color: impl Into<Rgba<u8>>,
+    ) -> &mut Self {
+        self.section_colors.insert(name.into(), color.into().0);
+        self
+    }
+
+    /// 设置文本颜色
+    pub fn text_color(&mut self, text_color: impl Into<Rgba<u8>>) -> &mut Self {
+        self.text_color = Some(text_color.into().0);
+        self
+    }
+
+    /// 设置最大缩放比例
+    pub fn max_scale(&mut self, max_scale: f32) -> &mut Self {
+        self.max_scale = Some(max_scale);
+        self
+    }
+
+    /// 设置字体文件路径
+    pub fn font(&mut self, font: impl Into<PathBuf>) -> &mut Self {
+        self.font = Some(font.into());
+        self
+    }
+
+    /// 设置文本背景框的圆角半径
+    pub fn radius(&mut self, radius: i32) -> &mut Self {
+        self.radius = Some(radius);
+        self
+    }
+
+    /// 设置行高
+    pub fn line_height(&mut self, line_height: f32) -> &mut Self {
+        self.line_height = Some(line_height);
+        self
+    }
+
+    /// 设置字间距
+    pub fn letter_spacing(&mut self, letter_spacing: f32) -> &mut Self {
+        self.letter_spacing = Some(letter_spacing);
+        self
+    }
+
+    /// 设置文本描边
+    pub fn text_outline(&mut self, outline: TextOutline) -> &mut Self {
+        self.text_outline = Some(outline);
+        self
+    }
+
+    /// 设置文本阴影
+    pub fn text_shadow(&mut self, shadow: TextShadow) -> &mut Self {
+        self.text_shadow = Some(shadow);
+        self
+    }
+
+    /// 设置是否解析文本中的标记
+    pub fn markup(&mut self, markup: bool) -> &mut Self {
+        self.markup = Some(markup);
+        self
+    }
+
+    pub(crate) fn font_mut(&mut self) -> Option<&mut PathBuf> {
+        self.font.as_mut()
+    }
+
+    /// 将样式覆盖合并到 `BigImg` 的默认样式上
+    ///
+    /// 覆盖的字号、圆角、描边和阴影按输出目标的缩放比例调整。
+    /// 覆盖的字体由 [`BigImg::style_font`] 加载，每个文件只读取一次。
+    ///
+    /// # Errors
+    /// - 如果字体文件读取失败或不是有效的字体，则返回 `Err`。
+    /// - 如果行高不是正数，则返回 `Err`。
+    ///
+    pub(crate) fn merge(&self, si: &BigImg) -> Result<Style> {
+        let font = match self.font {
+            // 覆盖主字体，保留回退字体
+            Some(ref path) => si.font.clone().with_primary_of(&si.style_font(path)?),
+            None => si.font.clone(),
+        };
+        if let Some(line_height) = self.line_height {
+            if line_height <= 0.0 {
+                return Err(err_new!(
+                    Kind::InvalidChunk,
+                    "Line height must be positive."
+                ));
+            }
+        }
+        let overrides = TextEffect {
+            outline: self.text_outline.map(Into::into),
+            shadow: self.text_shadow.map(Into::into),
+        }
+        .scaled(si.factor);
+        let section_colors = si
+            .sections
+            .iter()
+            .map(|section| {
+                if let Some(&color) = self.section_colors.get(section.name()) {
+                    return Rgba(color);
+                }
+                match (self.text_background_color, section.name()) {
+                    (Some((up, _)), Section::UP) => Rgba(up),
+                    (Some((_, down)), Section::DOWN) => Rgba(down),
+                    _ => section.color(),
+                }
+            })
+            .collect();
+        Ok(Style {
+            section_colors,
+            text_color: self.text_color.map_or(si.text_color, Rgba),
+            max_scale: self.max_scale.map_or(si.max_scale, |s| s * si.factor),
+            font,
+            line_height: self.line_height.unwrap_or(si.line_height),
+            letter_spacing: self.letter_spacing.unwrap_or(si.letter_spacing),
+            radius: self
+                .radius
+                .map_or(si.radius, |r| (r as f32 * si.factor).round() as i32),
+            effect: TextEffect {
+                outline: overrides.outline.or(si.text_effect.outline),
+                shadow: overrides.shadow.or(si.text_effect.shadow),
+            },
+            markup: self.markup.unwrap_or(si.markup),
+        })
+    }
+}
+
+impl BigImg<'_> {
+    /// 图像块样式中的字体，第一次使用时读取并解析，之后复用
+    ///
+    /// # Errors
+    /// - 如果字体文件读取失败或不是有效的字体，则返回 `Err`。
+    ///
+    pub(crate) fn style_font(&self, path: &Path) -> Result<FontChain> {
+        if let Some(font) = self.style_fonts.lock().unwrap().get(path) {
+            return Ok(font.clone());
+        }
+        // 在锁外读取文件，失败时下次使用仍会重试
+        let data = std::fs::read(path).map_err(|e| err_new_io!(e))?;
+        let font =
+            FontChain::from_data(data).map_err(|e| err_new!(Kind::InvalidFont, &e.to_string()))?;
+        let mut fonts = self.style_fonts.lock().unwrap();
+        Ok(fonts.entry(path.to_path_buf()).or_insert(font).clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_partial() {
+        let style: ChunkStyle =
+            serde_json::from_str(r#"{"text_color": [255, 0, 0, 255], "radius": 20}"#).unwrap();
+        assert_eq!(style.text_color, Some([255, 0, 0, 255]));
+        assert_eq!(style.radius, Some(20));
+        assert!(style.font.is_none());
+        assert!(!style.is_empty());
+        assert!(ChunkStyle::default().is_empty());
+    }
+
+    #[test]
+    fn test_markup_opt_in() {
+        let chunks = [];
+        let mut si = BigImg::new_with_default(Path::new("."), &chunks);
+        let text = "**5** [b]星[/b]";
+
+        // 默认原样绘制，开启后解析标记，图像块样式优先
+        let style = ChunkStyle::default().merge(&si).unwrap();
+        assert_eq!(style.runs(text)[0].len(), 1);
+        assert_eq!(style.runs(text)[0][0].text, text);
+        si.markup(true);
+        let style = ChunkStyle::default().merge(&si).unwrap();
+        let runs = &style.runs(text)[0];
+        assert_eq!((runs[0].text.as_str(), runs[0].bold), ("5", true));
+        let style = ChunkStyle::default().markup(false).merge(&si).unwrap();
+        assert_eq!(style.runs(text)[0][0].text, text);
+    }
+
+    #[test]
+    fn test_spacing() {
+        let chunks = [];
+        let mut si = BigImg::new_with_default(Path::new("."), &chunks);
+        si.line_height(1.2).letter_spacing(0.1);
+
+        // 图像块样式覆盖行高，字间距沿用默认值
+        let style = ChunkStyle::default().line_height(1.5).merge(&si).unwrap();
+        let text_style = style.spaced(TextStyle::new(60.0));
+        assert_eq!(
+            (text_style.line_height, text_style.letter_spacing),
+            (1.5, 0.1)
+        );
+        assert!(ChunkStyle::default().line_height(0.0).merge(&si).is_err());
+    }
+
+    #[test]
+    fn test_section_colors() {
+        let style: ChunkStyle = serde_json::from_str(
+            r#"{"text_background_color": [[1, 1, 1, 255], [2, 2, 2, 255]],
+                "section_colors": {"text_down": [3, 3, 3, 255], "footer": [4, 4, 4, 255]}}"#,
+        )
+        .unwrap();
+        let chunks = [];
+        let mut si = BigImg::new_with_default(Path::new("."), &chunks);
+        let mut sections = Section::preset();
+        sections.push(Section::new("footer", 1.0, [0, 0, 0, 255]));
+        sections.push(Section::new("other", 1.0, [5, 5, 5, 255]));
+        si.sections(sections);
+
+        // 按名称的覆盖优先，其余区域沿用上下区域的覆盖或区域自身的颜色
+        let colors = style.merge(&si).unwrap().section_colors;
+        assert_eq!(
+            colors,
+            [
+                Rgba([1, 1, 1, 255]),
+                Rgba([3, 3, 3, 255]),
+                Rgba([4, 4, 4, 255]),
+                Rgba([5, 5, 5, 255])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_style_font_loaded_once() {
+        use crate::imageproc::drawing::Fonts;
+        use ab_glyph::Font;
+
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/MiSans-Demibold.ttf");
+        let mut style = ChunkStyle::default();
+        style.font(&path);
+        let chunks = [];
+        let si = BigImg::new_with_default(Path::new("."), &chunks);
+
+        // 两次合并使用同一份已解析的字体，各输出目标的副本也共享
+        let a = style.merge(&si).unwrap().font;
+        let b = style.merge(&si.clone()).unwrap().font;
+        assert!(std::ptr::eq(
+            a.primary().font_data(),
+            b.primary().font_data()
+        ));
+        assert_eq!(si.style_fonts.lock().unwrap().len(), 1);
+
+        style.font("missing.ttf");
+        assert!(style.merge(&si).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use super::BigImg;
+use std::fmt::Write;
+
+/// 字幕
+///
+/// # Parameters
+///
+/// * `start`: 开始时间（秒）。
+/// * `end`: 结束时间（秒）。
+/// * `text`: 字幕文本。
+#[derive(Clone, Debug, PartialEq)]
+pub struct Subtitle {
+    pub start: f32,
+    pub end: f32,
+    pub text: String,
+}
+
+/// `SRT` 时间戳，如 `00:01:02,500`
+fn timestamp(seconds: f32) -> String {
+    let ms = (seconds * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// 生成 `SRT` 字幕文件内容
+pub(crate) fn srt(subtitles: &[Subtitle]) -> String {
+    let mut out = String::new();
+    for (i, subtitle) in subtitles.iter().enumerate() {
+        let _ = write!(
+            out,
+            "{}\n{} --> {}\n{}\n\n",
+            i + 1,
+            timestamp(subtitle.start),
+            timestamp(subtitle.end),
+            subtitle.text
+        );
+    }
+    out
+}
+
+impl BigImg<'_> {
+    /// 各图像块位于屏幕中央时显示的字幕
+    ///
+    /// 普通图像块显示标题，分隔块显示分组标题，时间与章节和计数器一致：
+    /// 从图像块左边缘滑到屏幕中央时开始，到右边缘滑到屏幕中央时结束，
+    /// 结尾时位于屏幕中央的图像块持续到视频结束。没有标题或不经过屏幕中央的图像块没有字幕。
+    ///
+    /// # Results
+    /// 返回字幕列表。
+    ///
+    #[must_use]
+    pub fn subtitles(&self) -> Vec<Subtitle> {
+        let offsets = self.offsets(self.chunks);
+        let last = self.max_x() + self.screen.0 / 2;
+        let duration = self.duration();
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !c.title().is_empty())
+            .filter_map(|(i, c)| {
+                if offsets[i] > last {
+                    return None;
+                }
+                let start = self.centre_time(offsets[i]);
+                let end = if offsets[i + 1] > last {
+                    duration
+                } else {
+                    self.centre_time(offsets[i + 1])
+                };
+                (start < end).then(|| Subtitle {
+                    start,
+                    end,
+                    text: c.title().to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swiping_img::Chunk;
+    use std::path::Path;
+
+    #[test]
+    fn test_subtitles() {
+        let chunk =
+            |name: &str| Chunk::from_source(Path::new("a.png"), vec![name.to_string()], vec![]);
+        let chunks = vec![
+            chunk("a1"),
+            chunk("a2"),
+            chunk("a3"),
+            Chunk::divider("B"),
+            chunk("b1"),
+            chunk("b2"),
+            chunk("b3"),
+        ];
+        let si = BigImg::new_with_default(Path::new("."), &chunks);
+        let subtitles = si.subtitles();
+
+        // 第一个图像块从未到达屏幕中央，第三个从封面开始，分隔块显示分组标题
+        let texts: Vec<_> = subtitles.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, ["a3", "B", "b1", "b2"]);
+        assert_eq!(subtitles[0].start, 0.0);
+        // 分隔块在 1440 像素处，滑到屏幕中央需要 (1440 - 960) / 160 秒
+        assert_eq!(subtitles[1].start, 3.0 + 3.0);
+        assert_eq!(subtitles[0].end, subtitles[1].start);
+        assert_eq!(subtitles[3].end, si.duration());
+
+        let srt = srt(&subtitles[1..2]);
+        assert_eq!(srt, "1\n00:00:06,000 --> 00:00:09,000\nB\n\n");
+    }
+}
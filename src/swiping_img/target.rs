@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+
+/// 视频编码参数
+///
+/// # Parameters
+///
+/// * `codec`: 视频编码器，为 `None` 时使用 `FFmpeg` 的默认编码器。
+/// * `preset`: 编码预设，如 `fast`、`slow`。
+/// * `crf`: 恒定质量因子，为 `None` 时使用编码器默认值。
+#[derive(Clone, Debug)]
+pub struct EncodingProfile {
+    codec: Option<String>,
+    preset: String,
+    crf: Option<u32>,
+}
+
+impl Default for EncodingProfile {
+    fn default() -> Self {
+        Self {
+            codec: None,
+            preset: String::from("fast"),
+            crf: None,
+        }
+    }
+}
+
+impl EncodingProfile {
+    /// 设置视频编码器
+    pub fn codec(&mut self, codec: impl Into<String>) -> &mut Self {
+        self.codec = Some(codec.into());
+        self
+    }
+
+    /// 设置编码预设
+    pub fn preset(&mut self, preset: impl Into<String>) -> &mut Self {
+        self.preset = preset.into();
+        self
+    }
+
+    /// 设置恒定质量因子
+    pub fn crf(&mut self, crf: u32) -> &mut Self {
+        self.crf = Some(crf);
+        self
+    }
+
+    /// 生成 `FFmpeg` 编码参数
+    pub(crate) fn args(&self) -> Vec<String> {
+        let mut args = Vec::with_capacity(6);
+        if let Some(ref codec) = self.codec {
+            args.extend(["-c:v".to_string(), codec.clone()]);
+        }
+        args.extend(["-preset".to_string(), self.preset.clone()]);
+        if let Some(crf) = self.crf {
+            args.extend(["-crf".to_string(), crf.to_string()]);
+        }
+        args
+    }
+}
+
+/// 输出目标
+///
+/// 同一次运行可以输出多个目标，布局按 `BigImg` 的逻辑设计等比缩放。
+///
+/// # Parameters
+///
+/// * `save_name`: 输出视频文件名。
+/// * `screen`: 输出分辨率（宽度，高度）。
+/// * `fps`: 输出帧率。
+/// * `profile`: 编码参数。
+#[derive(Clone, Debug)]
+pub struct OutputTarget {
+    save_name: PathBuf,
+    screen: (u32, u32),
+    fps: u32,
+    profile: EncodingProfile,
+}
+
+impl OutputTarget {
+    /// 创建输出目标
+    ///
+    /// # Panics
+    /// - 如果分辨率宽高或帧率为零，程序将 panic
+    ///
+    #[must_use]
+    pub fn new(save_name: impl Into<PathBuf>, screen: (u32, u32), fps: u32) -> Self {
+        assert!(
+            screen.0 != 0 && screen.1 != 0,
+            "Screen dimensions must be non-zero."
+        );
+        assert_ne!(fps, 0, "Fps must be non-zero.");
+        Self {
+            save_name: save_name.into(),
+            screen,
+            fps,
+            profile: EncodingProfile::default(),
+        }
+    }
+
+    /// 设置编码参数
+    pub fn profile(&mut self, profile: EncodingProfile) -> &mut Self {
+        self.profile = profile;
+        self
+    }
+
+    pub(crate) fn save_name(&self) -> &Path {
+        &self.save_name
+    }
+
+    pub(crate) fn screen(&self) -> (u32, u32) {
+        self.screen
+    }
+
+    pub(crate) fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    pub(crate) fn encoding(&self) -> &EncodingProfile {
+        &self.profile
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_args() {
+        assert_eq!(EncodingProfile::default().args(), ["-preset", "fast"]);
+        let mut profile = EncodingProfile::default();
+        profile.codec("libx265").preset("slow").crf(20);
+        assert_eq!(
+            profile.args(),
+            ["-c:v", "libx265", "-preset", "slow", "-crf", "20"]
+        );
+    }
+}
@@ -0,0 +1,379 @@
+use super::{
+    chunk::{Chunk, Overflowed, TextBox},
+    BigImg,
+};
+use crate::imageproc::{
+    drawing::{rich_text_overflow, Overflow, TextStyle},
+    rect::Rect,
+};
+use std::fmt;
+
+/// 单张图片允许的最大边长
+const MAX_DIMENSION: u32 = 16384;
+
+/// 图像块的最小宽度，文本框左右各留 10 像素边距，更窄时放不下文本
+const MIN_CHUNK_WIDTH: u32 = 21;
+
+/// 问题的严重程度
+///
+/// * `Error`: 无法渲染，`run` 会在开始前拒绝。
+/// * `Warning`: 可以渲染，但结果可能不理想。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// 某个图像块的问题
+///
+/// # Parameters
+///
+/// * `index`: 图像块序号（从 0 开始）。
+/// * `title`: 图像块标题。
+/// * `severity`: 严重程度。
+/// * `message`: 问题描述。
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    pub index: usize,
+    pub title: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// 所有图像块的检查结果
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    problems: Vec<Problem>,
+}
+
+impl Report {
+    /// 所有问题，按图像块顺序排列
+    #[must_use]
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    /// 是否存在无法渲染的问题
+    #[must_use]
+    pub fn has_errors(&self) -> bool {
+        self.problems.iter().any(|p| p.severity == Severity::Error)
+    }
+
+    /// 是否没有任何问题
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// 记录渲染时按溢出处理方式截断或裁剪的文本
+    ///
+    /// 相邻分段重叠的图像块会绘制多次，相同的问题只记录一次。
+    ///
+    /// # Parameters
+    /// - `index`: 图像块序号。
+    /// - `chunk`: 图像块。
+    /// - `screen`: 渲染的屏幕分辨率，区分不同的输出目标。
+    /// - `overflows`: 绘制时放不下的文本。
+    ///
+    pub(super) fn rendered(
+        &mut self,
+        index: usize,
+        chunk: &Chunk,
+        screen: (u32, u32),
+        overflows: &[Overflowed],
+    ) {
+        for (text, o) in overflows {
+            let problem = Problem {
+                index,
+                title: chunk.title().to_string(),
+                severity: Severity::Warning,
+                message: format!(
+                    "text {text:?} rendered at {}x{} overflows at {:.1}px, needs {}x{} ({:?})",
+                    screen.0, screen.1, o.scale, o.width, o.height, o.overflow
+                ),
+            };
+            if !self.problems.contains(&problem) {
+                self.problems.push(problem);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for p in &self.problems {
+            let severity = match p.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            writeln!(
+                f,
+                "#{} {:?} [{severity}] {}",
+                p.index + 1,
+                p.title,
+                p.message
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl BigImg<'_> {
+    /// 在渲染前检查所有图像块
+    ///
+    /// 检查图片是否存在、能否识别以及尺寸是否合理，各文本区域是否有文本，
+    /// 并找出在 `min_scale` 下仍放不下的文本。按 `overflow` 处理方式，
+    /// `Error` 时报告为错误，否则报告为警告，以便检查受影响的图像块。
+    /// 所有问题汇总到一份报告中，而不是遇到第一个问题就停止。
+    /// 尺寸合理的图片会完整解码一次，损坏的像素数据在渲染任何分段之前就会报告，
+    /// 解码后的图片随即释放，不占用渲染时的内存。
+    ///
+    /// # Results
+    /// 返回检查报告。
+    ///
+    #[must_use]
+    pub fn validate(&self) -> Report {
+        let mut report = Report::default();
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let mut push = |severity, message: String| {
+                report.problems.push(Problem {
+                    index,
+                    title: chunk.title().to_string(),
+                    severity,
+                    message,
+                });
+            };
+
+            let width = self.chunk_width(chunk);
+            if width < MIN_CHUNK_WIDTH {
+                push(
+                    Severity::Error,
+                    format!(
+                        "chunk is {width}px wide (width {}), too narrow to draw",
+                        chunk.width()
+                    ),
+                );
+            }
+
+            for source in chunk.sources() {
+                if !source.exists() {
+                    push(Severity::Error, format!("image not found: {source}"));
+                    continue;
+                }
+                // 先读取文件头检查尺寸，避免解码尺寸不合理的图片
+                match source.dimensions() {
+                    Err(e) => push(Severity::Error, format!("cannot decode {source}: {e}")),
+                    Ok((w, h)) if w == 0 || h == 0 || w > MAX_DIMENSION || h > MAX_DIMENSION => {
+                        push(
+                            Severity::Error,
+                            format!("unreasonable image size {w}x{h}: {source}"),
+                        );
+                    }
+                    Ok(_) => {
+                        if let Err(e) = source.open() {
+                            push(Severity::Error, format!("cannot decode {source}: {e}"));
+                        }
+                    }
+                }
+            }
+
+            let style = match chunk.style().merge(self) {
+                Ok(style) => style,
+                Err(e) => {
+                    push(Severity::Error, format!("invalid style: {e}"));
+                    continue;
+                }
+            };
+            let boxes = match chunk.text_boxes(self) {
+                Ok(boxes) => boxes,
+                Err(e) => {
+                    push(Severity::Error, format!("invalid layout: {e}"));
+                    continue;
+                }
+            };
+            // 与绘制时相同的排版，找出最小字号下仍放不下的文本
+            let overflow = |line: &str, rect: Rect, text_style: TextStyle| {
+                let runs = style.runs(line);
+                let o = rich_text_overflow(
+                    &style.font,
+                    &runs,
+                    rect.width(),
+                    rect.height(),
+                    &text_style,
+                )?;
+                let severity = match o.overflow {
+                    Overflow::Error => Severity::Error,
+                    Overflow::Ellipsis | Overflow::Clip => Severity::Warning,
+                };
+                let message = format!(
+                    "overflows at {:.1}px, needs {}x{} in {}x{} ({:?})",
+                    o.scale,
+                    o.width,
+                    o.height,
+                    rect.width(),
+                    rect.height(),
+                    o.overflow
+                );
+                Some((severity, message))
+            };
+
+            // 分隔块只绘制标题
+            if chunk.is_divider() {
+                let rect = Chunk::divider_title_rect(self, self.chunk_width(chunk));
+                let text_style = style.spaced(self.limit_text(TextStyle::new(style.max_scale)));
+                if let Some((severity, message)) = overflow(chunk.title(), rect, text_style) {
+                    push(severity, format!("title {:?} {message}", chunk.title()));
+                }
+                continue;
+            }
+            for TextBox { section, lines, .. } in boxes {
+                if chunk.text(section.name()).is_empty() {
+                    push(
+                        Severity::Error,
+                        format!("section {:?} has no text", section.name()),
+                    );
+                }
+                let text_style = style.spaced(
+                    section
+                        .text_style(style.max_scale, self.min_scale)
+                        .overflow(self.overflow),
+                );
+                for (rect, line) in lines {
+                    if let Some((severity, message)) = overflow(line, rect, text_style) {
+                        push(
+                            severity,
+                            format!("text {line:?} in section {:?} {message}", section.name()),
+                        );
+                    }
+                }
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::swiping_img::Chunk;
+    use std::path::Path;
+
+    #[test]
+    fn test_validate() {
+        let long = "很长的标题".repeat(20);
+        let chunks: Vec<Chunk> = serde_json::from_str(&format!(
+            r#"[
+                {{"pic_path": "missing.png", "text_up": ["{long}"]}},
+                {{"pic_path": "missing.png", "text_up": ["a"], "text_down": []}}
+            ]"#
+        ))
+        .unwrap();
+        let mut si = BigImg::new_with_default(Path::new("."), &chunks);
+        // 标题会自动换行，提高最小字号使其仍然放不下
+        si.min_scale(48.0);
+        let report = si.validate();
+        assert!(report.has_errors());
+
+        let problems: Vec<_> = report
+            .problems()
+            .iter()
+            .map(|p| (p.index, p.severity))
+            .collect();
+        // 两个图像块都缺少图片和下方文本，第一个图像块的标题过长
+        assert_eq!(
+            problems,
+            vec![
+                (0, Severity::Error),
+                (0, Severity::Warning),
+                (0, Severity::Error),
+                (1, Severity::Error),
+                (1, Severity::Error),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncated_image() {
+        use image::{DynamicImage, ImageFormat};
+        use std::io::Cursor;
+
+        let mut bytes = Vec::new();
+        DynamicImage::new_rgba8(64, 64)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        let text = || vec![String::from("a")];
+        let chunks = [
+            Chunk::from_source(bytes.clone(), text(), text()),
+            // 文件头完整，像素数据被截断
+            Chunk::from_source(bytes[..bytes.len() / 2].to_vec(), text(), text()),
+        ];
+        let si = BigImg::new_with_default(Path::new("."), &chunks);
+        let report = si.validate();
+        let problems: Vec<_> = report
+            .problems()
+            .iter()
+            .map(|p| (p.index, p.severity))
+            .collect();
+        assert_eq!(problems, [(1, Severity::Error)]);
+        assert!(report.problems()[0].message.starts_with("cannot decode"));
+    }
+
+    #[test]
+    fn test_chunk_width() {
+        // 宽度倍数不是正数时拒绝解析
+        for width in ["0", "-1", "0.0"] {
+            let json = format!(r#"{{"pic_path": "a.png", "width": {width}}}"#);
+            assert!(serde_json::from_str::<Chunk>(&json).is_err());
+        }
+
+        // 接近 0 的宽度可以解析，检查时报告为错误而不是 panic
+        let chunks: Vec<Chunk> = serde_json::from_str(
+            r#"[
+                {"pic_path": "a.png", "text_up": ["a"], "text_down": ["b"], "width": 0.001},
+                {"pic_path": "", "text_up": ["A"], "metadata": {"divider": true}, "width": 0.001}
+            ]"#,
+        )
+        .unwrap();
+        let si = BigImg::new_with_default(Path::new("."), &chunks);
+        let report = si.validate();
+        for index in 0..2 {
+            assert!(report.problems().iter().any(|p| p.index == index
+                && p.severity == Severity::Error
+                && p.message.contains("too narrow")));
+        }
+        let imgs = [image::DynamicImage::new_rgba8(10, 10)];
+        for chunk in &chunks {
+            chunk.draw_with(&si, &imgs).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_rendered() {
+        use crate::imageproc::drawing::Overflow;
+        use image::DynamicImage;
+
+        let long = "很长的标题".repeat(20);
+        let chunks: Vec<Chunk> = serde_json::from_str(&format!(
+            r#"[{{"pic_path": "a.png", "text_up": ["{long}"], "text_down": ["a"]}}]"#
+        ))
+        .unwrap();
+        let mut si = BigImg::new_with_default(Path::new("."), &chunks);
+        si.min_scale(48.0);
+        let imgs = [DynamicImage::new_rgba8(10, 10)];
+
+        // 截断绘制的文本记录为警告，重复绘制只记录一次
+        let (_, overflows) = chunks[0].draw_with(&si, &imgs).unwrap();
+        assert_eq!(overflows.len(), 1);
+        assert_eq!(overflows[0].0, long);
+        let mut report = Report::default();
+        report.rendered(0, &chunks[0], si.screen, &overflows);
+        report.rendered(0, &chunks[0], si.screen, &overflows);
+        assert_eq!(report.problems().len(), 1);
+        assert_eq!(report.problems()[0].severity, Severity::Warning);
+
+        // 溢出处理方式为 `Error` 时拒绝绘制
+        si.overflow(Overflow::Error);
+        let err = chunks[0].draw_with(&si, &imgs).unwrap_err();
+        assert!(format!("{err:?}").contains("TextOverflow"));
+    }
+}
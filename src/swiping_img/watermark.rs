@@ -0,0 +1,260 @@
+use super::filter::FilterGraph;
+use crate::{
+    err_new_image,
+    error::Result,
+    imageproc::drawing::{text_size, DrawText, FontChain, TextEffect},
+};
+use image::{DynamicImage, Rgba};
+use std::path::{Path, PathBuf};
+
+/// 水印内容
+#[derive(Clone, Debug)]
+pub enum WatermarkContent {
+    /// 图片水印，如频道 logo
+    Image(PathBuf),
+    /// 文字水印，使用 `BigImg` 的字体渲染
+    Text {
+        text: String,
+        color: Rgba<u8>,
+        scale: f32,
+    },
+}
+
+/// 水印所在的屏幕角落
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Corner {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// 视频水印
+///
+/// 水印固定在屏幕上，不随图像块滚动，
+/// 封面、滚动和结尾部分都会叠加。
+///
+/// # Parameters
+///
+/// * `content`: 水印内容。
+/// * `corner`: 水印所在的屏幕角落。
+/// * `offset`: 水印距离角落的偏移（水平，垂直）。
+/// * `scale`: 水印的缩放比例。
+/// * `opacity`: 水印的不透明度，范围 `0.0..=1.0`。
+/// * `fade_in`: 视频开头水印淡入的时长（秒），为 `0` 时不淡入。
+/// * `effect`: 文字水印的描边和阴影，使文字在任意画面上都清晰可读。
+#[derive(Clone, Debug)]
+pub struct Watermark {
+    content: WatermarkContent,
+    corner: Corner,
+    offset: (u32, u32),
+    scale: f32,
+    opacity: f32,
+    fade_in: f32,
+    effect: TextEffect<Rgba<u8>>,
+}
+
+impl Watermark {
+    /// 水印图片在工作路径中的文件名
+    pub(crate) const PIC_NAME: &'static str = "watermark.png";
+
+    /// 创建图片水印
+    ///
+    /// # Parameters
+    /// - `path`: 水印图片路径。
+    ///
+    #[must_use]
+    pub fn image(path: impl Into<PathBuf>) -> Self {
+        Self::with_content(WatermarkContent::Image(path.into()))
+    }
+
+    /// 创建文字水印
+    ///
+    /// # Parameters
+    /// - `text`: 水印文字。
+    /// - `color`: 文字颜色。
+    /// - `scale`: 文字字号。
+    ///
+    #[must_use]
+    pub fn text(text: impl Into<String>, color: impl Into<Rgba<u8>>, scale: f32) -> Self {
+        Self::with_content(WatermarkContent::Text {
+            text: text.into(),
+            color: color.into(),
+            scale,
+        })
+    }
+
+    fn with_content(content: WatermarkContent) -> Self {
+        Self {
+            content,
+            corner: Corner::default(),
+            offset: (40, 40),
+            scale: 1.0,
+            opacity: 1.0,
+            fade_in: 0.0,
+            effect: TextEffect::default(),
+        }
+    }
+
+    /// 设置水印位置
+    ///
+    /// # Parameters
+    /// - `corner`: 水印所在的屏幕角落。
+    /// - `offset`: 水印距离角落的偏移（水平，垂直）。
+    ///
+    pub fn position(&mut self, corner: Corner, offset: (u32, u32)) -> &mut Self {
+        self.corner = corner;
+        self.offset = offset;
+        self
+    }
+
+    /// 设置缩放比例
+    ///
+    /// # Panics
+    /// - 如果 `scale` 不是正数，程序将 panic
+    ///
+    pub fn scale(&mut self, scale: f32) -> &mut Self {
+        assert!(scale > 0.0, "Watermark scale must be positive.");
+        self.scale = scale;
+        self
+    }
+
+    /// 设置不透明度
+    ///
+    /// # Panics
+    /// - 如果 `opacity` 不在 `0.0..=1.0` 范围内，程序将 panic
+    ///
+    pub fn opacity(&mut self, opacity: f32) -> &mut Self {
+        assert!(
+            (0.0..=1.0).contains(&opacity),
+            "Watermark opacity must be within 0.0..=1.0."
+        );
+        self.opacity = opacity;
+        self
+    }
+
+    /// 设置淡入时长（秒）
+    ///
+    /// # Panics
+    /// - 如果 `fade_in` 为负数，程序将 panic
+    ///
+    pub fn fade_in(&mut self, fade_in: f32) -> &mut Self {
+        assert!(fade_in >= 0.0, "Watermark fade in must be non-negative.");
+        self.fade_in = fade_in;
+        self
+    }
+
+    /// 设置文字水印的描边和阴影，图片水印忽略该设置
+    ///
+    /// # Parameters
+    /// - `effect`: 描边和阴影，尺寸以文字水印的原始字号为准。
+    ///
+    pub fn effect(&mut self, effect: TextEffect<Rgba<u8>>) -> &mut Self {
+        self.effect = effect;
+        self
+    }
+
+    /// 按输出目标的缩放比例调整水印大小和偏移
+    ///
+    /// 文字水印按目标尺寸重新栅格化，放大字号和描边阴影而不是放大图片，
+    /// 用户设置的缩放比例保持不变。
+    pub(crate) fn scale_by(&mut self, factor: f32) {
+        match self.content {
+            WatermarkContent::Image(_) => self.scale *= factor,
+            WatermarkContent::Text { ref mut scale, .. } => {
+                *scale *= factor;
+                self.effect = self.effect.scaled(factor);
+            }
+        }
+        self.offset = (
+            (self.offset.0 as f32 * factor).round() as u32,
+            (self.offset.1 as f32 * factor).round() as u32,
+        );
+    }
+
+    /// 将水印图片写入工作路径
+    ///
+    /// # Errors
+    /// - 如果水印图片打开或保存失败，则返回 `Err`。
+    ///
+    pub(crate) fn prepare(&self, work_dir: &Path, font: &FontChain) -> Result<()> {
+        let img = match self.content {
+            WatermarkContent::Image(ref path) => {
+                image::open(path).map_err(|e| err_new_image!(e))?
+            }
+            WatermarkContent::Text {
+                ref text,
+                color,
+                scale,
+            } => {
+                // 为描边和阴影留出边距
+                let margin = self.effect.margin() + 2;
+                let (w, h) = text_size(scale, font, text);
+                let mut img = DynamicImage::new_rgba8(w + margin * 2, h + margin * 2);
+                let m = margin as i32;
+                img.draw_text_with_effect_mut(color, m, m, scale, font, text, &self.effect);
+                img
+            }
+        };
+        img.save(work_dir.join(Self::PIC_NAME))
+            .map_err(|e| err_new_image!(e))
+    }
+
+    /// 将水印叠加到滤镜图上
+    ///
+    /// # Parameters
+    /// - `graph`: 当前部分视频的滤镜图。
+    /// - `first`: 是否为视频的第一部分，只有第一部分会淡入。
+    ///
+    pub(crate) fn apply(&self, graph: &mut FilterGraph, first: bool) {
+        let index = graph.input(Path::new(Self::PIC_NAME));
+        let mut source = format!(
+            "[{index}]format=rgba,scale=iw*{0}:ih*{0},colorchannelmixer=aa={1}",
+            self.scale, self.opacity
+        );
+        if first && self.fade_in > 0.0 {
+            source.push_str(&format!(",fade=t=in:st=0:d={}:alpha=1", self.fade_in));
+        }
+        let (ox, oy) = self.offset;
+        let (x, y) = match self.corner {
+            Corner::TopLeft => (format!("{ox}"), format!("{oy}")),
+            Corner::TopRight => (format!("W-w-{ox}"), format!("{oy}")),
+            Corner::BottomLeft => (format!("{ox}"), format!("H-h-{oy}")),
+            Corner::BottomRight => (format!("W-w-{ox}"), format!("H-h-{oy}")),
+        };
+        graph.overlay(&source, &format!("x={x}:y={y}:shortest=1"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imageproc::drawing::Outline;
+
+    #[test]
+    fn test_scale_by() {
+        // 图片水印由 FFmpeg 缩放
+        let mut watermark = Watermark::image("logo.png");
+        watermark.scale(0.5).scale_by(2.0);
+        assert_eq!(watermark.scale, 1.0);
+        assert_eq!(watermark.offset, (80, 80));
+
+        // 文字水印按目标尺寸栅格化，不再放大图片
+        let mut watermark = Watermark::text("channel", Rgba([255, 255, 255, 255]), 40.0);
+        watermark.scale(0.5).effect(TextEffect {
+            outline: Some(Outline {
+                width: 2.0,
+                color: Rgba([0, 0, 0, 255]),
+            }),
+            shadow: None,
+        });
+        watermark.scale_by(2.0);
+        assert_eq!(watermark.scale, 0.5);
+        assert!(matches!(
+            watermark.content,
+            WatermarkContent::Text { scale, .. } if scale == 80.0
+        ));
+        assert_eq!(watermark.effect.outline.unwrap().width, 4.0);
+    }
+}